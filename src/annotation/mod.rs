@@ -0,0 +1,307 @@
+use ab_glyph::FontVec;
+use enum_ordinalize::Ordinalize;
+use image::{imageops, ImageFormat, Rgba, RgbaImage};
+use once_cell::unsync::OnceCell;
+
+use crate::{
+    config::{AllEnumValues, RedactionStyle},
+    error::Error,
+    payload::Payload,
+    processing::{decode_image, encode_image, watermark::load_font, Region},
+};
+
+use self::render::draw_annotation;
+
+pub mod render;
+
+/// A point in image coordinates.
+pub type Point = (f32, f32);
+
+/// The tools of the annotation editor.
+#[derive(Debug, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Arrow,
+    Rectangle,
+    Highlight,
+    Text,
+    Step,
+    Redact,
+    Crop,
+}
+
+impl AllEnumValues for Tool {
+    fn all() -> Vec<Tool> {
+        vec![
+            Self::Arrow,
+            Self::Rectangle,
+            Self::Highlight,
+            Self::Text,
+            Self::Step,
+            Self::Redact,
+            Self::Crop,
+        ]
+    }
+}
+
+/// The shapes which can be drawn onto a screenshot.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Arrow { start: Point, end: Point },
+    Rectangle(Region),
+    // Tints the region with the annotation color, keeping the content readable
+    Highlight(Region),
+    Text { position: Point, text: String },
+    // A numbered circle marking the steps of a guide
+    Step { center: Point, number: u32 },
+    // Destroys the pixels of the region, including earlier annotations
+    Redact { region: Region, style: RedactionStyle },
+}
+
+/// A shape drawn onto a screenshot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub shape: Shape,
+    pub color: Rgba<u8>,
+    // Line width of arrows and rectangles, font size of text and radius of steps
+    pub size: f32,
+}
+
+/// The edits which can be undone.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct EditState {
+    annotations: Vec<Annotation>,
+    crop: Option<Region>,
+}
+
+/// A screenshot with its annotations, independent of any display.
+///
+/// All annotations use the coordinates of the original image, the crop is applied when
+/// rendering.
+pub struct AnnotationDocument {
+    image: RgbaImage,
+    state: EditState,
+    undo: Vec<EditState>,
+    redo: Vec<EditState>,
+    // Only loaded once text is drawn
+    font: OnceCell<FontVec>,
+}
+
+impl AnnotationDocument {
+    pub fn new(image: RgbaImage) -> Self {
+        Self {
+            image,
+            state: EditState::default(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            font: OnceCell::new(),
+        }
+    }
+
+    /// Creates a document from the image data of a payload.
+    pub fn from_payload(payload: &Payload) -> Result<Self, Error> {
+        Ok(Self::new(decode_image(payload)?.to_rgba8()))
+    }
+
+    pub fn annotations(&self) -> &Vec<Annotation> {
+        &self.state.annotations
+    }
+
+    pub fn crop_region(&self) -> Option<Region> {
+        self.state.crop
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// The number of the next step annotation.
+    pub fn next_step(&self) -> u32 {
+        let steps = self
+            .state
+            .annotations
+            .iter()
+            .filter(|annotation| matches!(annotation.shape, Shape::Step { .. }))
+            .count();
+        steps as u32 + 1
+    }
+
+    /// Converts a point of the rendered (cropped) image into image coordinates.
+    pub fn to_image_point(&self, point: Point) -> Point {
+        match self.state.crop {
+            Some(crop) => (point.0 + crop.x as f32, point.1 + crop.y as f32),
+            None => point,
+        }
+    }
+
+    pub fn add(&mut self, annotation: Annotation) {
+        self.edit(|state| state.annotations.push(annotation));
+    }
+
+    /// Crops the image to the given region of the original image, replacing the previous crop.
+    ///
+    /// Regions outside of the image are ignored.
+    pub fn crop(&mut self, region: Region) {
+        if let Some(region) = region.clip(self.image.width(), self.image.height()) {
+            self.edit(|state| state.crop = Some(region));
+        }
+    }
+
+    /// Reverts the last edit.
+    ///
+    /// # Returns
+    /// Whether an edit was reverted
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop() {
+            Some(state) => {
+                self.redo.push(std::mem::replace(&mut self.state, state));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Restores the last reverted edit.
+    ///
+    /// # Returns
+    /// Whether an edit was restored
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(state) => {
+                self.undo.push(std::mem::replace(&mut self.state, state));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Draws all annotations onto the image and applies the crop.
+    ///
+    /// # Returns
+    /// The rendered image
+    pub fn render(&self) -> Result<RgbaImage, Error> {
+        self.render_with(None)
+    }
+
+    /// Renders the image like `render`, including an annotation which is still being drawn.
+    ///
+    /// # Returns
+    /// The rendered image
+    pub fn render_with(&self, pending: Option<&Annotation>) -> Result<RgbaImage, Error> {
+        let mut image = self.image.clone();
+        for annotation in self.state.annotations.iter().chain(pending) {
+            let font = match annotation.shape {
+                Shape::Text { .. } | Shape::Step { .. } => {
+                    Some(self.font.get_or_try_init(|| load_font(None))?)
+                }
+                _ => None,
+            };
+            draw_annotation(&mut image, annotation, font);
+        }
+
+        Ok(match self.state.crop {
+            Some(crop) => {
+                imageops::crop_imm(&image, crop.x, crop.y, crop.width, crop.height).to_image()
+            }
+            None => image,
+        })
+    }
+
+    /// Renders the document into a PNG payload replacing the original screenshot.
+    ///
+    /// # Returns
+    /// The edited payload
+    pub fn export(&self, original: &Payload) -> Result<Payload, Error> {
+        let image = image::DynamicImage::ImageRgba8(self.render()?);
+        Ok(Payload::new(
+            original.path().with_extension("png"),
+            encode_image(&image, ImageFormat::Png)?,
+            "image/png".into(),
+        ))
+    }
+
+    /// Applies an edit, making it undoable and dropping all reverted edits.
+    fn edit<F: FnOnce(&mut EditState)>(&mut self, edit: F) {
+        self.undo.push(self.state.clone());
+        self.redo.clear();
+        edit(&mut self.state);
+    }
+}
+
+/// The result of editing a screenshot in the annotation editor.
+pub enum EditorAction {
+    // Uploads the edited screenshot
+    Upload(Payload),
+    // Saves the edited screenshot without uploading it
+    Save(Payload),
+    Cancel,
+}
+
+#[cfg(test)]
+pub mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use crate::processing::Region;
+
+    use super::{Annotation, AnnotationDocument, Shape};
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    fn rectangle(x: u32, y: u32) -> Annotation {
+        Annotation {
+            shape: Shape::Rectangle(Region {
+                x,
+                y,
+                width: 20,
+                height: 20,
+            }),
+            color: RED,
+            size: 2.0,
+        }
+    }
+
+    #[test]
+    pub fn test_undo_redo() {
+        let mut document = AnnotationDocument::new(RgbaImage::from_pixel(100, 100, WHITE));
+        document.add(rectangle(10, 10));
+        document.add(rectangle(50, 50));
+        assert!(document.undo());
+        assert_eq!(document.annotations(), &vec![rectangle(10, 10)]);
+
+        assert!(document.redo());
+        assert!(!document.redo());
+        assert_eq!(document.annotations().len(), 2);
+
+        // New edits drop the reverted ones
+        document.undo();
+        document.add(rectangle(70, 70));
+        assert!(!document.can_redo());
+        assert_eq!(document.annotations()[1], rectangle(70, 70));
+    }
+
+    #[test]
+    pub fn test_render() {
+        let mut document = AnnotationDocument::new(RgbaImage::from_pixel(100, 100, WHITE));
+        document.add(rectangle(10, 10));
+        document.crop(Region {
+            x: 5,
+            y: 5,
+            width: 50,
+            height: 200,
+        });
+
+        let rendered = document.render().unwrap();
+        assert_eq!(rendered.dimensions(), (50, 95));
+        // The outline is drawn onto the edge of the region, the crop moves it by 5 pixels
+        assert_eq!(*rendered.get_pixel(5, 10), RED);
+        assert_eq!(*rendered.get_pixel(15, 15), WHITE);
+        assert_eq!(document.to_image_point((5.0, 10.0)), (10.0, 15.0));
+
+        document.undo();
+        assert_eq!(document.render().unwrap().dimensions(), (100, 100));
+    }
+}
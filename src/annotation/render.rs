@@ -0,0 +1,169 @@
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use imageproc::{
+    drawing::{draw_filled_circle_mut, draw_polygon_mut, draw_text_mut, text_size},
+    point::Point as PolygonPoint,
+};
+
+use crate::processing::{redact::redact_region, Region};
+
+use super::{Annotation, Point, Shape};
+
+// Length of an arrow head relative to the line width
+const ARROW_HEAD_LENGTH: f32 = 4.0;
+// Minimum length of an arrow head, keeps thin arrows recognizable
+const MIN_ARROW_HEAD_LENGTH: f32 = 10.0;
+// Font size of step numbers relative to the circle radius
+const STEP_FONT_SCALE: f32 = 1.2;
+
+/// Draws a single annotation onto an image.
+///
+/// The font is only used by text and step annotations, which are skipped without font.
+pub fn draw_annotation(image: &mut RgbaImage, annotation: &Annotation, font: Option<&FontVec>) {
+    let color = annotation.color;
+    let size = annotation.size.max(1.0);
+    match &annotation.shape {
+        Shape::Arrow { start, end } => draw_arrow(image, *start, *end, size, color),
+        Shape::Rectangle(region) => {
+            let (left, top) = (region.x as f32, region.y as f32);
+            let (right, bottom) = (left + region.width as f32, top + region.height as f32);
+            let corners = [(left, top), (right, top), (right, bottom), (left, bottom)];
+            for (index, corner) in corners.iter().enumerate() {
+                draw_line(image, *corner, corners[(index + 1) % 4], size, color);
+            }
+        }
+        Shape::Highlight(region) => highlight(image, region, color),
+        Shape::Redact { region, style } => redact_region(image, region, *style),
+        Shape::Text { position, text } => {
+            let Some(font) = font else {
+                return;
+            };
+            let line_height = font.as_scaled(PxScale::from(size)).height();
+            for (index, line) in text.lines().enumerate() {
+                let y = position.1 + index as f32 * line_height;
+                draw_text_mut(
+                    image,
+                    color,
+                    position.0.round() as i32,
+                    y.round() as i32,
+                    size,
+                    font,
+                    line,
+                );
+            }
+        }
+        Shape::Step { center, number } => {
+            let center_pixel = (center.0.round() as i32, center.1.round() as i32);
+            draw_filled_circle_mut(image, center_pixel, size.round() as i32, color);
+
+            let Some(font) = font else {
+                return;
+            };
+            let text = number.to_string();
+            let scale = size * STEP_FONT_SCALE;
+            let (width, height) = text_size(scale, font, &text);
+            draw_text_mut(
+                image,
+                contrast_color(color),
+                center_pixel.0 - width as i32 / 2,
+                center_pixel.1 - height as i32 / 2,
+                scale,
+                font,
+                &text,
+            );
+        }
+    }
+}
+
+/// Draws a line with an arrow head at its end.
+fn draw_arrow(image: &mut RgbaImage, start: Point, end: Point, width: f32, color: Rgba<u8>) {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    let head_length = (width * ARROW_HEAD_LENGTH)
+        .max(MIN_ARROW_HEAD_LENGTH)
+        .min(length);
+    if head_length < 1.0 {
+        return;
+    }
+
+    let (unit_x, unit_y) = (dx / length, dy / length);
+    let base = (end.0 - unit_x * head_length, end.1 - unit_y * head_length);
+    let half_width = head_length * 0.6;
+    draw_line(image, start, base, width, color);
+    draw_polygon(
+        image,
+        &[
+            end,
+            (base.0 - unit_y * half_width, base.1 + unit_x * half_width),
+            (base.0 + unit_y * half_width, base.1 - unit_x * half_width),
+        ],
+        color,
+    );
+}
+
+/// Draws a line of the given width with round ends.
+fn draw_line(image: &mut RgbaImage, start: Point, end: Point, width: f32, color: Rgba<u8>) {
+    let radius = width / 2.0;
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length >= 0.5 {
+        let (offset_x, offset_y) = (-dy / length * radius, dx / length * radius);
+        draw_polygon(
+            image,
+            &[
+                (start.0 + offset_x, start.1 + offset_y),
+                (end.0 + offset_x, end.1 + offset_y),
+                (end.0 - offset_x, end.1 - offset_y),
+                (start.0 - offset_x, start.1 - offset_y),
+            ],
+            color,
+        );
+    }
+
+    if radius >= 1.0 {
+        for point in [start, end] {
+            let center = (point.0.round() as i32, point.1.round() as i32);
+            draw_filled_circle_mut(image, center, radius.floor() as i32, color);
+        }
+    }
+}
+
+/// Fills a polygon, skipping degenerate polygons whose corners collapse into one pixel.
+fn draw_polygon(image: &mut RgbaImage, points: &[Point], color: Rgba<u8>) {
+    let mut polygon = points
+        .iter()
+        .map(|point| PolygonPoint::new(point.0.round() as i32, point.1.round() as i32))
+        .collect::<Vec<_>>();
+    polygon.dedup();
+    // The polygon may not be closed explicitly
+    while polygon.len() > 1 && polygon.first() == polygon.last() {
+        polygon.pop();
+    }
+    if polygon.len() > 2 {
+        draw_polygon_mut(image, &polygon, color);
+    }
+}
+
+/// Multiplies the pixels of a region with the color, like a text marker.
+fn highlight(image: &mut RgbaImage, region: &Region, color: Rgba<u8>) {
+    let Some(region) = region.clip(image.width(), image.height()) else {
+        return;
+    };
+    for y in region.y..region.y + region.height {
+        for x in region.x..region.x + region.width {
+            let pixel = image.get_pixel_mut(x, y);
+            for channel in 0..3 {
+                pixel[channel] = (pixel[channel] as u16 * color[channel] as u16 / 255) as u8;
+            }
+        }
+    }
+}
+
+/// Picks black or white, whichever is more readable on the given color.
+fn contrast_color(color: Rgba<u8>) -> Rgba<u8> {
+    let luminance = 0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32;
+    match luminance > 160.0 {
+        true => Rgba([0, 0, 0, 255]),
+        false => Rgba([255, 255, 255, 255]),
+    }
+}
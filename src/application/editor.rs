@@ -0,0 +1,420 @@
+use adw::prelude::*;
+use enum_ordinalize::Ordinalize;
+use gtk4::{gdk, glib};
+use image::{Rgba, RgbaImage};
+use relm4::{prelude::*, ComponentSender};
+use tokio::sync::oneshot;
+
+use crate::{
+    annotation::{Annotation, AnnotationDocument, EditorAction, Point, Shape, Tool},
+    config::{AllEnumValues, RedactionStyle},
+    error::Error,
+    payload::Payload,
+    processing::Region,
+};
+
+// The colors which can be picked for annotations
+const COLORS: [(&str, [u8; 3]); 6] = [
+    ("Red", [229, 57, 53]),
+    ("Yellow", [255, 235, 59]),
+    ("Green", [67, 160, 71]),
+    ("Blue", [30, 136, 229]),
+    ("Black", [0, 0, 0]),
+    ("White", [255, 255, 255]),
+];
+
+// Sizes of the annotations in image pixels
+const LINE_WIDTH: f32 = 4.0;
+const FONT_SIZE: f32 = 28.0;
+const STEP_RADIUS: f32 = 18.0;
+
+pub struct EditorWindow {
+    document: AnnotationDocument,
+    original: Payload,
+    // Receives the action once the editor is closed
+    reply: Option<oneshot::Sender<EditorAction>>,
+    tool: Tool,
+    color: Rgba<u8>,
+    text: String,
+    redaction: RedactionStyle,
+    // Start of the current drag in image coordinates
+    drag_start: Option<Point>,
+    texture: gdk::MemoryTexture,
+}
+
+pub struct EditorInit {
+    document: AnnotationDocument,
+    original: Payload,
+    redaction: RedactionStyle,
+    reply: oneshot::Sender<EditorAction>,
+}
+
+#[derive(Debug)]
+pub enum EditorMessage {
+    SelectTool(u32),
+    SelectColor(u32),
+    ChangeText(String),
+    // Points in coordinates of the rendered image
+    DragBegin(Point),
+    DragUpdate(Point),
+    DragEnd(Point),
+    Undo,
+    Redo,
+    Upload,
+    Save,
+    Cancel,
+}
+
+#[relm4::component(pub)]
+impl Component for EditorWindow {
+    type Init = EditorInit;
+    type Input = EditorMessage;
+    type Output = ();
+    type CommandOutput = ();
+
+    view! {
+        adw::Window {
+            set_title: Some("Edit Screenshot"),
+            set_default_size: (1000, 700),
+            connect_close_request[sender] => move |_| {
+                sender.input(EditorMessage::Cancel);
+                glib::Propagation::Stop
+            },
+
+            adw::ToolbarView {
+                add_top_bar = &adw::HeaderBar {
+                    pack_start = &gtk4::Button {
+                        set_label: "Cancel",
+                        connect_clicked => EditorMessage::Cancel,
+                    },
+                    pack_start = &gtk4::Button {
+                        set_icon_name: "edit-undo-symbolic",
+                        set_tooltip_text: Some("Undo"),
+                        #[watch]
+                        set_sensitive: model.document.can_undo(),
+                        connect_clicked => EditorMessage::Undo,
+                    },
+                    pack_start = &gtk4::Button {
+                        set_icon_name: "edit-redo-symbolic",
+                        set_tooltip_text: Some("Redo"),
+                        #[watch]
+                        set_sensitive: model.document.can_redo(),
+                        connect_clicked => EditorMessage::Redo,
+                    },
+
+                    #[wrap(Some)]
+                    set_title_widget = &gtk4::Box {
+                        set_spacing: 6,
+
+                        gtk4::DropDown::from_strings(&tool_names) {
+                            set_tooltip_text: Some("Tool"),
+                            connect_selected_notify[sender] => move |dropdown| {
+                                sender.input(EditorMessage::SelectTool(dropdown.selected()));
+                            },
+                        },
+                        gtk4::DropDown::from_strings(&color_names) {
+                            set_tooltip_text: Some("Color"),
+                            connect_selected_notify[sender] => move |dropdown| {
+                                sender.input(EditorMessage::SelectColor(dropdown.selected()));
+                            },
+                        },
+                        gtk4::Entry {
+                            set_placeholder_text: Some("Text"),
+                            #[watch]
+                            set_visible: model.tool == Tool::Text,
+                            connect_changed[sender] => move |entry| {
+                                sender.input(EditorMessage::ChangeText(entry.text().to_string()));
+                            },
+                        },
+                    },
+
+                    pack_end = &gtk4::Button {
+                        set_label: "Upload",
+                        add_css_class: "suggested-action",
+                        connect_clicked => EditorMessage::Upload,
+                    },
+                    pack_end = &gtk4::Button {
+                        set_label: "Save",
+                        connect_clicked => EditorMessage::Save,
+                    },
+                },
+
+                #[wrap(Some)]
+                #[name = "picture"]
+                set_content = &gtk4::Picture {
+                    set_hexpand: true,
+                    set_vexpand: true,
+                    set_can_shrink: true,
+                    #[watch]
+                    set_paintable: Some(&model.texture),
+                },
+            }
+        }
+    }
+
+    fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let image = init
+            .document
+            .render()
+            .expect("Rendering without annotations cannot fail");
+        let model = Self {
+            document: init.document,
+            original: init.original,
+            reply: Some(init.reply),
+            tool: Tool::Arrow,
+            color: color_at(0),
+            text: String::new(),
+            redaction: init.redaction,
+            drag_start: None,
+            texture: create_texture(image),
+        };
+
+        let tool_names = Tool::all()
+            .into_iter()
+            .map(|tool| tool.into())
+            .collect::<Vec<&str>>();
+        let color_names = COLORS.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        let widgets = view_output!();
+
+        let drag = gtk4::GestureDrag::new();
+        let picture = widgets.picture.clone();
+        let drag_sender = sender.clone();
+        drag.connect_drag_begin(move |_, x, y| {
+            if let Some(point) = picture_point(&picture, x, y) {
+                drag_sender.input(EditorMessage::DragBegin(point));
+            }
+        });
+        let picture = widgets.picture.clone();
+        let drag_sender = sender.clone();
+        drag.connect_drag_update(move |gesture, offset_x, offset_y| {
+            if let Some((x, y)) = gesture.start_point() {
+                if let Some(point) = picture_point(&picture, x + offset_x, y + offset_y) {
+                    drag_sender.input(EditorMessage::DragUpdate(point));
+                }
+            }
+        });
+        let picture = widgets.picture.clone();
+        let drag_sender = sender.clone();
+        drag.connect_drag_end(move |gesture, offset_x, offset_y| {
+            if let Some((x, y)) = gesture.start_point() {
+                if let Some(point) = picture_point(&picture, x + offset_x, y + offset_y) {
+                    drag_sender.input(EditorMessage::DragEnd(point));
+                }
+            }
+        });
+        widgets.picture.add_controller(drag);
+
+        root.set_application(Some(&relm4::main_adw_application()));
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>, root: &Self::Root) {
+        match message {
+            EditorMessage::SelectTool(index) => {
+                self.tool = Tool::from_ordinal(index as i8).unwrap_or(Tool::Arrow);
+            }
+            EditorMessage::SelectColor(index) => self.color = color_at(index as usize),
+            EditorMessage::ChangeText(text) => self.text = text,
+            EditorMessage::DragBegin(point) => {
+                self.drag_start = Some(self.document.to_image_point(point));
+            }
+            EditorMessage::DragUpdate(point) => {
+                if let Some(start) = self.drag_start {
+                    let end = self.document.to_image_point(point);
+                    let pending = match self.tool {
+                        // The crop is previewed as outline of the selected region
+                        Tool::Crop => Some(Annotation {
+                            shape: Shape::Rectangle(Region::from_corners(start, end)),
+                            color: self.color,
+                            size: 2.0,
+                        }),
+                        _ => self.annotation(start, end),
+                    };
+                    self.refresh(pending.as_ref());
+                }
+            }
+            EditorMessage::DragEnd(point) => {
+                if let Some(start) = self.drag_start.take() {
+                    let end = self.document.to_image_point(point);
+                    match self.tool {
+                        Tool::Crop => self.document.crop(Region::from_corners(start, end)),
+                        _ => {
+                            if let Some(annotation) = self.annotation(start, end) {
+                                self.document.add(annotation);
+                            }
+                        }
+                    }
+                    self.refresh(None);
+                }
+            }
+            EditorMessage::Undo => {
+                self.document.undo();
+                self.refresh(None);
+            }
+            EditorMessage::Redo => {
+                self.document.redo();
+                self.refresh(None);
+            }
+            EditorMessage::Upload => self.finish(root, EditorAction::Upload),
+            EditorMessage::Save => self.finish(root, EditorAction::Save),
+            EditorMessage::Cancel => {
+                if let Some(reply) = self.reply.take() {
+                    let _ = reply.send(EditorAction::Cancel);
+                }
+                root.destroy();
+            }
+        }
+    }
+}
+
+impl EditorWindow {
+    /// Creates the annotation of the selected tool for a drag between two points.
+    ///
+    /// Text and steps are placed at the start of the drag.
+    fn annotation(&self, start: Point, end: Point) -> Option<Annotation> {
+        let (shape, size) = match self.tool {
+            Tool::Arrow => (Shape::Arrow { start, end }, LINE_WIDTH),
+            Tool::Rectangle | Tool::Highlight | Tool::Redact => {
+                let region = Region::from_corners(start, end);
+                if region.width == 0 || region.height == 0 {
+                    return None;
+                }
+                match self.tool {
+                    Tool::Rectangle => (Shape::Rectangle(region), LINE_WIDTH),
+                    Tool::Highlight => (Shape::Highlight(region), LINE_WIDTH),
+                    _ => (
+                        Shape::Redact {
+                            region,
+                            style: self.redaction,
+                        },
+                        LINE_WIDTH,
+                    ),
+                }
+            }
+            Tool::Text if !self.text.is_empty() => (
+                Shape::Text {
+                    position: start,
+                    text: self.text.clone(),
+                },
+                FONT_SIZE,
+            ),
+            Tool::Step => (
+                Shape::Step {
+                    center: start,
+                    number: self.document.next_step(),
+                },
+                STEP_RADIUS,
+            ),
+            Tool::Text | Tool::Crop => return None,
+        };
+
+        Some(Annotation {
+            shape,
+            color: self.color,
+            size,
+        })
+    }
+
+    /// Renders the document into the displayed texture.
+    fn refresh(&mut self, pending: Option<&Annotation>) {
+        match self.document.render_with(pending) {
+            Ok(image) => self.texture = create_texture(image),
+            Err(err) => log::error!("Failed to render annotations: {err}"),
+        }
+    }
+
+    /// Exports the edited screenshot and closes the editor.
+    ///
+    /// If the export fails, e.g. because the font of text annotations is missing, the editor
+    /// stays open and shows the error.
+    fn finish<F: FnOnce(Payload) -> EditorAction>(&mut self, root: &adw::Window, action: F) {
+        match self.document.export(&self.original) {
+            Ok(payload) => {
+                if let Some(reply) = self.reply.take() {
+                    let _ = reply.send(action(payload));
+                }
+                root.destroy();
+            }
+            Err(err) => {
+                log::error!("Failed to export edited screenshot: {err}");
+                let dialog = adw::AlertDialog::builder()
+                    .heading("Failed to export screenshot")
+                    .body(err.to_string())
+                    .build();
+                dialog.add_responses(&[("close", "Close")]);
+                dialog.set_close_response("close");
+                dialog.present(Some(root));
+            }
+        }
+    }
+}
+
+/// Opens the annotation editor for a screenshot and waits until it is closed.
+///
+/// # Returns
+/// The action chosen by the user, including the edited screenshot
+pub async fn edit_capture(
+    payload: Payload,
+    redaction: RedactionStyle,
+) -> Result<EditorAction, Error> {
+    let document = AnnotationDocument::from_payload(&payload)?;
+    let (reply, receiver) = oneshot::channel();
+
+    // Windows can only be created on the main thread
+    glib::MainContext::default().invoke(move || {
+        let mut editor = EditorWindow::builder()
+            .launch(EditorInit {
+                document,
+                original: payload,
+                redaction,
+                reply,
+            })
+            .detach();
+        editor.widget().present();
+        editor.detach_runtime();
+    });
+
+    Ok(receiver.await.unwrap_or(EditorAction::Cancel))
+}
+
+/// Converts a point of the picture widget into coordinates of the displayed image.
+fn picture_point(picture: &gtk4::Picture, x: f64, y: f64) -> Option<Point> {
+    let paintable = picture.paintable()?;
+    let (width, height) = (
+        paintable.intrinsic_width() as f64,
+        paintable.intrinsic_height() as f64,
+    );
+    if width <= 0.0 || height <= 0.0 {
+        return None;
+    }
+
+    // The picture scales the image to fit and centers it
+    let scale = (picture.width() as f64 / width).min(picture.height() as f64 / height);
+    let offset_x = (picture.width() as f64 - width * scale) / 2.0;
+    let offset_y = (picture.height() as f64 - height * scale) / 2.0;
+    Some((
+        ((x - offset_x) / scale) as f32,
+        ((y - offset_y) / scale) as f32,
+    ))
+}
+
+fn create_texture(image: RgbaImage) -> gdk::MemoryTexture {
+    let (width, height) = image.dimensions();
+    gdk::MemoryTexture::new(
+        width as i32,
+        height as i32,
+        gdk::MemoryFormat::R8g8b8a8,
+        &glib::Bytes::from_owned(image.into_raw()),
+        width as usize * 4,
+    )
+}
+
+fn color_at(index: usize) -> Rgba<u8> {
+    let [red, green, blue] = COLORS.get(index).unwrap_or(&COLORS[0]).1;
+    Rgba([red, green, blue, 255])
+}
@@ -6,12 +6,14 @@ use super::save_with_report;
 
 pub struct GeneralPage {
     cleanup_state: bool,
+    annotate_state: bool,
     toaster: Toaster,
 }
 
 #[derive(Debug)]
 pub enum GeneralPageMessage {
     SetCleanup(bool),
+    SetAnnotate(bool),
 }
 
 #[relm4::component(pub async)]
@@ -42,6 +44,16 @@ impl SimpleAsyncComponent for GeneralPage {
                                 connect_active_notify[sender] => move |switch| {
                                     sender.input(GeneralPageMessage::SetCleanup(switch.is_active()))
                                 },
+                            },
+
+                            #[name = "annotate"]
+                            adw::SwitchRow {
+                                set_title: "Annotate",
+                                set_subtitle: "Opens an editor to annotate the screenshot before upload",
+                                set_active: model.annotate_state,
+                                connect_active_notify[sender] => move |switch| {
+                                    sender.input(GeneralPageMessage::SetAnnotate(switch.is_active()))
+                                },
                             }
                         }
                     },
@@ -59,6 +71,7 @@ impl SimpleAsyncComponent for GeneralPage {
 
         let model = Self {
             cleanup_state: config.cleanup,
+            annotate_state: config.annotate,
             toaster: Toaster::default(),
         };
 
@@ -76,6 +89,14 @@ impl SimpleAsyncComponent for GeneralPage {
                 self.cleanup_state = active;
                 config.cleanup = active;
 
+                save_with_report(&config, &self.toaster).await;
+            }
+            GeneralPageMessage::SetAnnotate(active) => {
+                let mut config = CONFIG.lock().await;
+
+                self.annotate_state = active;
+                config.annotate = active;
+
                 save_with_report(&config, &self.toaster).await;
             }
         }
@@ -22,6 +22,7 @@ use crate::{
 
 use self::tray::ShareShotTray;
 
+pub(crate) mod editor;
 pub(crate) mod factory;
 pub(crate) mod general;
 pub(crate) mod tray;
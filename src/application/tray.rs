@@ -1,6 +1,12 @@
 use ksni::Tray;
 
-use crate::{application::ApplicationMessage, capture::capture_and_upload};
+use crate::{
+    application::ApplicationMessage,
+    capture::{
+        capture_and_upload, capture_code, capture_text, paste_clipboard, select_file, upload_file,
+        CaptureOptions,
+    },
+};
 
 pub(crate) struct ShareShotTray {
     pub sender: relm4::Sender<ApplicationMessage>,
@@ -27,7 +33,7 @@ impl Tray for ShareShotTray {
                 label: "Capture".into(),
                 activate: Box::new(|_| {
                     tokio::spawn(async move {
-                        capture_and_upload()
+                        capture_and_upload(&CaptureOptions::default())
                             .await
                             .expect("Failed to capture, screenshot has been canceled?");
                     });
@@ -35,6 +41,58 @@ impl Tray for ShareShotTray {
                 ..Default::default()
             }
             .into(),
+            StandardItem {
+                label: "Capture Text".into(),
+                activate: Box::new(|_| {
+                    tokio::spawn(async move {
+                        if let Err(err) = capture_text(&CaptureOptions::default()).await {
+                            log::error!("Failed to capture text: {err}");
+                        }
+                    });
+                }),
+                ..Default::default()
+            }
+            .into(),
+            StandardItem {
+                label: "Scan Code".into(),
+                activate: Box::new(|_| {
+                    tokio::spawn(async move {
+                        if let Err(err) = capture_code().await {
+                            log::error!("Failed to scan code: {err}");
+                        }
+                    });
+                }),
+                ..Default::default()
+            }
+            .into(),
+            StandardItem {
+                label: "Upload File…".into(),
+                activate: Box::new(|_| {
+                    tokio::spawn(async move {
+                        let result = match select_file().await {
+                            Ok(path) => upload_file(&path, &CaptureOptions::default()).await,
+                            Err(err) => Err(err),
+                        };
+                        if let Err(err) = result {
+                            log::error!("Failed to upload file: {err}");
+                        }
+                    });
+                }),
+                ..Default::default()
+            }
+            .into(),
+            StandardItem {
+                label: "Paste Clipboard Text".into(),
+                activate: Box::new(|_| {
+                    tokio::spawn(async move {
+                        if let Err(err) = paste_clipboard(&CaptureOptions::default()).await {
+                            log::error!("Failed to paste clipboard text: {err}");
+                        }
+                    });
+                }),
+                ..Default::default()
+            }
+            .into(),
             StandardItem {
                 label: "Settings".into(),
                 activate: Box::new(|tray: &mut Self| {
@@ -2,7 +2,10 @@ use std::{collections::BTreeMap, str};
 
 use crate::{
     application::CONFIG,
-    config::{AllEnumValues, RequestMethod, UploadStrategy},
+    config::{AllEnumValues, HttpConfig, RequestMethod, UploadStrategy, PRIMARY_PROFILE},
+    curl::parse_curl_command,
+    payload::Payload,
+    upload::preview_upload,
 };
 use adw::prelude::*;
 use enum_ordinalize::Ordinalize;
@@ -14,10 +17,14 @@ pub struct UploadPage {
     current_url: String,
     current_file_form_name: String,
     current_url_parser: String,
+    current_body_template: String,
+    current_graphql_query: String,
     selected_request_method: i8,
     selected_upload_strategy: i8,
     headers: AsyncFactoryVecDeque<VisualizedHeader>,
     toaster: Toaster,
+    // Set if the configuration was replaced and all fields have to be updated
+    reloaded: bool,
 }
 
 #[derive(Debug)]
@@ -30,6 +37,11 @@ pub enum UploadPageMessage {
     ChangeUrl(String),
     ChangeFileFormName(String),
     ChangeUrlParser(String),
+    ChangeBodyTemplate(String),
+    ChangeGraphQlQuery(String),
+    PreviewRequest,
+    ShowCurlImport,
+    ImportCurl(String),
 }
 
 #[relm4::component(pub async)]
@@ -48,16 +60,26 @@ impl SimpleAsyncComponent for UploadPage {
                 adw::PreferencesPage {
                     adw::PreferencesGroup {
                         set_title: "General",
+                        #[wrap(Some)]
+                        set_header_suffix = &gtk4::Button {
+                            set_css_classes: &vec!["flat"],
+                            set_label: "Import from curl",
+                            connect_clicked[sender] => move |_| {
+                                sender.input(UploadPageMessage::ShowCurlImport);
+                            }
+                        },
                         gtk4::ListBox {
                             add_css_class: "boxed-list",
                             set_selection_mode: gtk4::SelectionMode::None,
 
                             adw::EntryRow {
                                 set_title: "URL",
+                                #[track(model.reloaded)]
+                                #[block_signal(url_handler)]
                                 set_text: &model.current_url,
                                 connect_changed[sender] => move |entry| {
                                     sender.input(UploadPageMessage::ChangeUrl(entry.text().to_string()));
-                                }
+                                } @url_handler,
                             },
                             adw::ComboRow {
                                 set_title_lines: 1,
@@ -65,38 +87,77 @@ impl SimpleAsyncComponent for UploadPage {
                                 set_title: "Request Method",
                                 set_subtitle: "The REST method to use when making the upload request",
                                 set_model: Some(&UploadPage::extract_strings_from::<RequestMethod>()),
+                                #[track(model.reloaded)]
+                                #[block_signal(request_method_handler)]
                                 set_selected: model.selected_request_method as u32,
                                 connect_selected_notify[sender] => move |item| {
                                 sender.input(UploadPageMessage::ChangeRequestMethod(item.selected()));
-                                },
+                                } @request_method_handler,
                             },
                             adw::ComboRow {
                                 set_title_lines: 1,
                                 set_subtitle_lines: 1,
                                 set_title: "Upload Strategy",
-                                set_subtitle: "The method to use for attaching the image to the REST request",
+                                set_subtitle: "The method to use for attaching the file to the REST request",
                                 set_model: Some(&UploadPage::extract_strings_from::<UploadStrategy>()),
+                                #[track(model.reloaded)]
+                                #[block_signal(upload_strategy_handler)]
                                 set_selected: model.selected_upload_strategy as u32,
                                 connect_selected_notify[sender] => move |item| {
                                 sender.input(UploadPageMessage::ChangeUploadStrategy(item.selected()));
-                                },
+                                } @upload_strategy_handler,
                             },
                             #[name(multipart_file_name)]
                             adw::EntryRow {
                                 set_title: "Multipart File Name",
+                                #[track(model.reloaded)]
+                                #[block_signal(file_form_name_handler)]
                                 set_text: &model.current_file_form_name,
                                 #[watch]
                                 set_visible: model.selected_upload_strategy == UploadStrategy::Multipart.ordinal() as i8,
                                 connect_changed[sender] => move |entry| {
                                     sender.input(UploadPageMessage::ChangeFileFormName(entry.text().to_string()));
-                                }
+                                } @file_form_name_handler,
+                            },
+                            adw::EntryRow {
+                                set_title: "Body Template",
+                                set_tooltip_text: Some("Template Variables:\n* {base64} - The base64 encoded file\n* {file_name}, {mime_type}, {size} - Details about the file\nUses the default template of the upload strategy if empty"),
+                                #[track(model.reloaded)]
+                                #[block_signal(body_template_handler)]
+                                set_text: &model.current_body_template,
+                                #[watch]
+                                set_visible: UploadStrategy::from_ordinal(model.selected_upload_strategy)
+                                    .is_some_and(|strategy| strategy.default_body_template().is_some()),
+                                connect_changed[sender] => move |entry| {
+                                    sender.input(UploadPageMessage::ChangeBodyTemplate(entry.text().to_string()));
+                                } @body_template_handler,
+                            },
+                            adw::EntryRow {
+                                set_title: "GraphQL Mutation",
+                                set_tooltip_text: Some("The mutation has to declare the uploaded file as `$file` variable, e.g.\nmutation ($file: Upload!) { upload(file: $file) { url } }"),
+                                #[track(model.reloaded)]
+                                #[block_signal(graphql_query_handler)]
+                                set_text: &model.current_graphql_query,
+                                #[watch]
+                                set_visible: model.selected_upload_strategy == UploadStrategy::GraphQl.ordinal() as i8,
+                                connect_changed[sender] => move |entry| {
+                                    sender.input(UploadPageMessage::ChangeGraphQlQuery(entry.text().to_string()));
+                                } @graphql_query_handler,
                             },
                             adw::EntryRow {
                                 set_title: "Response Parse Pattern",
                                 set_tooltip_text: Some("Parser Options:\n* $raw$ - Copies the raw response content into clipboard\n* $json:data.key$ Copies the JSON value at `data.key` into clipboard"),
+                                #[track(model.reloaded)]
+                                #[block_signal(url_parser_handler)]
                                 set_text: &model.current_url_parser,
                                 connect_changed[sender] => move |entry| {
                                     sender.input(UploadPageMessage::ChangeUrlParser(entry.text().to_string()));
+                                } @url_parser_handler,
+                            },
+                            adw::ButtonRow {
+                                set_title: "Preview Request",
+                                connect_activated[sender] => move |_| {
+                                    sender.input(UploadPageMessage::PreviewRequest);
                                 }
                             },
                         }
@@ -140,22 +201,36 @@ impl SimpleAsyncComponent for UploadPage {
                 VisualizedHeaderMessage::Delete(index) => UploadPageMessage::RemoveHeader(index),
             });
 
-        for (key, value) in &config.upload_server.headers {
+        for (key, value) in &config.upload_server.http.headers {
             headers.guard().push_back((key.clone(), value.clone()));
         }
 
         let model = Self {
-            current_url: config.upload_server.url.clone(),
+            current_url: config.upload_server.http.url.clone(),
             current_file_form_name: config
                 .upload_server
+                .http
                 .file_form_name
                 .clone()
                 .unwrap_or_default(),
-            current_url_parser: config.upload_server.url_parser.clone(),
-            selected_request_method: config.upload_server.request_method.ordinal(),
-            selected_upload_strategy: config.upload_server.upload_strategy.ordinal(),
+            current_url_parser: config.upload_server.http.url_parser.clone(),
+            current_body_template: config
+                .upload_server
+                .http
+                .body_template
+                .clone()
+                .unwrap_or_default(),
+            current_graphql_query: config
+                .upload_server
+                .http
+                .graphql_query
+                .clone()
+                .unwrap_or_default(),
+            selected_request_method: config.upload_server.http.request_method.ordinal(),
+            selected_upload_strategy: config.upload_server.http.upload_strategy.ordinal(),
             headers,
             toaster: Toaster::default(),
+            reloaded: false,
         };
 
         let toast_overlay = model.toaster.overlay_widget();
@@ -165,7 +240,9 @@ impl SimpleAsyncComponent for UploadPage {
         AsyncComponentParts { model, widgets }
     }
 
-    async fn update(&mut self, message: Self::Input, _sender: AsyncComponentSender<Self>) {
+    async fn update(&mut self, message: Self::Input, sender: AsyncComponentSender<Self>) {
+        self.reloaded = false;
+
         match message {
             UploadPageMessage::AddHeader => {
                 self.headers
@@ -202,6 +279,23 @@ impl SimpleAsyncComponent for UploadPage {
                 self.current_url_parser = url_parser.clone();
                 self.save_without_headers().await;
             }
+            UploadPageMessage::ChangeBodyTemplate(body_template) => {
+                self.current_body_template = body_template.clone();
+                self.save_without_headers().await;
+            }
+            UploadPageMessage::ChangeGraphQlQuery(graphql_query) => {
+                self.current_graphql_query = graphql_query.clone();
+                self.save_without_headers().await;
+            }
+            UploadPageMessage::PreviewRequest => {
+                self.show_preview().await;
+            }
+            UploadPageMessage::ShowCurlImport => {
+                self.show_curl_import(sender);
+            }
+            UploadPageMessage::ImportCurl(command) => {
+                self.import_curl(&command).await;
+            }
         }
     }
 }
@@ -210,17 +304,25 @@ impl UploadPage {
     async fn save_without_headers(&mut self) {
         let mut config = CONFIG.lock().await;
 
-        config.upload_server.set_url(self.current_url.clone());
+        config.upload_server.http.set_url(self.current_url.clone());
         config
             .upload_server
+            .http
             .set_file_form_name(self.current_file_form_name.clone());
         config
             .upload_server
+            .http
             .set_url_parser(self.current_url_parser.clone());
-        config.upload_server.set_request_method(
+        config.upload_server.http.set_body_template(
+            Some(self.current_body_template.clone()).filter(|template| !template.is_empty()),
+        );
+        config.upload_server.http.set_graphql_query(
+            Some(self.current_graphql_query.clone()).filter(|query| !query.is_empty()),
+        );
+        config.upload_server.http.set_request_method(
             RequestMethod::from_ordinal(self.selected_request_method).unwrap_or_default(),
         );
-        config.upload_server.set_upload_strategy(
+        config.upload_server.http.set_upload_strategy(
             UploadStrategy::from_ordinal(self.selected_upload_strategy).unwrap_or_default(),
         );
 
@@ -237,9 +339,151 @@ impl UploadPage {
             .for_each(|header| {
                 new_headers.insert(header.key.clone(), header.value.clone());
             });
-        config.upload_server.headers = new_headers;
+        config.upload_server.http.headers = new_headers;
+
+        save_with_report(&config, &self.toaster).await;
+    }
+
+    async fn show_preview(&self) {
+        let requests = match preview_upload(PRIMARY_PROFILE, &Payload::placeholder()).await {
+            Ok(requests) => requests,
+            Err(err) => {
+                self.toaster.add_toast(
+                    adw::Toast::builder()
+                        .title("Failed to preview request")
+                        .timeout(10)
+                        .build(),
+                );
+                log::error!("Failed to preview request: {}", err);
+                return;
+            }
+        };
+
+        let text = requests
+            .iter()
+            .map(|request| request.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let curl = requests
+            .iter()
+            .map(|request| request.to_curl())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let text_view = gtk4::TextView::builder()
+            .editable(false)
+            .monospace(true)
+            .wrap_mode(gtk4::WrapMode::WordChar)
+            .build();
+        text_view.buffer().set_text(&text);
+        let scrolled_window = gtk4::ScrolledWindow::builder()
+            .min_content_width(500)
+            .min_content_height(300)
+            .child(&text_view)
+            .build();
+
+        let dialog = adw::AlertDialog::builder()
+            .heading("Request Preview")
+            .body("The request is not sent, secrets are masked")
+            .extra_child(&scrolled_window)
+            .build();
+        dialog.add_responses(&[("copy", "Copy as curl"), ("close", "Close")]);
+        dialog.set_close_response("close");
+        dialog.set_default_response(Some("close"));
+        dialog.connect_response(Some("copy"), move |dialog, _| {
+            dialog.clipboard().set_text(&curl);
+        });
+        dialog.present(Some(self.toaster.overlay_widget()));
+    }
+
+    fn show_curl_import(&self, sender: AsyncComponentSender<Self>) {
+        let text_view = gtk4::TextView::builder()
+            .monospace(true)
+            .wrap_mode(gtk4::WrapMode::WordChar)
+            .build();
+        let scrolled_window = gtk4::ScrolledWindow::builder()
+            .min_content_width(500)
+            .min_content_height(150)
+            .child(&text_view)
+            .build();
 
+        let dialog = adw::AlertDialog::builder()
+            .heading("Import from curl")
+            .body("Paste a curl command uploading a file, the current configuration is replaced")
+            .extra_child(&scrolled_window)
+            .build();
+        dialog.add_responses(&[("cancel", "Cancel"), ("import", "Import")]);
+        dialog.set_response_appearance("import", adw::ResponseAppearance::Suggested);
+        dialog.set_close_response("cancel");
+        dialog.connect_response(Some("import"), move |_, _| {
+            let buffer = text_view.buffer();
+            let command = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+            sender.input(UploadPageMessage::ImportCurl(command.to_string()));
+        });
+        dialog.present(Some(self.toaster.overlay_widget()));
+    }
+
+    async fn import_curl(&mut self, command: &str) {
+        let import = match parse_curl_command(command) {
+            Ok(import) => import,
+            Err(err) => {
+                self.toaster.add_toast(
+                    adw::Toast::builder()
+                        .title(err.to_string())
+                        .timeout(10)
+                        .build(),
+                );
+                return;
+            }
+        };
+
+        let mut config = CONFIG.lock().await;
+        config.upload_server.http = import.config;
         save_with_report(&config, &self.toaster).await;
+        self.reload(&config.upload_server.http);
+
+        match import.warnings.is_empty() {
+            true => self.toaster.add_toast(
+                adw::Toast::builder()
+                    .title("Imported curl command")
+                    .timeout(5)
+                    .build(),
+            ),
+            false => {
+                let dialog = adw::AlertDialog::builder()
+                    .heading("Imported curl command")
+                    .body(
+                        import
+                            .warnings
+                            .iter()
+                            .map(|warning| format!("• {warning}"))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    )
+                    .build();
+                dialog.add_response("close", "Close");
+                dialog.present(Some(self.toaster.overlay_widget()));
+            }
+        }
+    }
+
+    /// Replaces all displayed values with the given configuration.
+    fn reload(&mut self, config: &HttpConfig) {
+        self.current_url = config.url.clone();
+        self.current_file_form_name = config.file_form_name.clone().unwrap_or_default();
+        self.current_url_parser = config.url_parser.clone();
+        self.current_body_template = config.body_template.clone().unwrap_or_default();
+        self.current_graphql_query = config.graphql_query.clone().unwrap_or_default();
+        self.selected_request_method = config.request_method.ordinal();
+        self.selected_upload_strategy = config.upload_strategy.ordinal();
+
+        let mut headers = self.headers.guard();
+        headers.clear();
+        for (key, value) in &config.headers {
+            headers.push_back((key.clone(), value.clone()));
+        }
+
+        self.reloaded = true;
     }
 
     fn extract_strings_from<T>() -> gtk4::StringList
@@ -0,0 +1,167 @@
+use std::path::Path;
+
+use ashpd::url::Url;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use regex::Regex;
+use tokio::process::Command;
+
+use crate::error::Error;
+
+// Exit status of zbarimg if the image was read but contains no codes
+const NO_CODES_STATUS: i32 = 4;
+
+/// A QR code or barcode decoded by zbar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedCode {
+    // The symbology reported by zbar, e.g. `QR-Code` or `EAN-13`
+    pub symbology: String,
+    pub content: String,
+}
+
+impl DecodedCode {
+    /// The content of the code if it is a web link, which can be opened in the browser.
+    pub fn url(&self) -> Option<Url> {
+        Url::parse(self.content.trim())
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+    }
+}
+
+/// Decodes all QR codes and barcodes of an image using zbarimg.
+///
+/// # Returns
+/// The decoded codes in the order reported by zbar, an error if the image contains none
+pub async fn decode_codes(path: &Path) -> Result<Vec<DecodedCode>, Error> {
+    let output = Command::new("zbarimg")
+        .args(["--xml", "--quiet"])
+        .arg(path)
+        .output()
+        .await
+        .map_err(|err| Error::CodeScan(format!("failed to start zbarimg: {err}")))?;
+
+    let codes = match output.status.code() {
+        Some(0) => parse_xml(&String::from_utf8_lossy(&output.stdout)),
+        Some(NO_CODES_STATUS) => Vec::new(),
+        _ => {
+            return Err(Error::CodeScan(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))
+        }
+    };
+    match codes.is_empty() {
+        true => Err(Error::CodeScan("no QR code or barcode was found".into())),
+        false => Ok(codes),
+    }
+}
+
+/// Parses the symbols of the XML output of zbarimg.
+///
+/// Binary content is reported base64 encoded and decoded lossily, CDATA sections split by zbar
+/// to escape `]]>` are joined again.
+fn parse_xml(xml: &str) -> Vec<DecodedCode> {
+    let symbol = Regex::new(
+        r"(?s)<symbol type='([^']+)'[^>]*>.*?<data( format='base64')?[^>]*><!\[CDATA\[(.*?)\]\]></data>",
+    )
+    .expect("The symbol pattern is valid");
+
+    symbol
+        .captures_iter(xml)
+        .filter_map(|captures| {
+            let data = captures[3].replace("]]><![CDATA[", "");
+            let content = match captures.get(2) {
+                Some(_) => {
+                    let bytes = STANDARD.decode(data.split_whitespace().collect::<String>());
+                    String::from_utf8_lossy(&bytes.ok()?).to_string()
+                }
+                None => data,
+            };
+            Some(DecodedCode {
+                symbology: captures[1].to_string(),
+                content,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use image::{imageops, Luma};
+    use qrcode::{Color, QrCode};
+
+    use super::{decode_codes, parse_xml, DecodedCode};
+
+    const XML: &str = "<barcodes xmlns='http://zbar.sourceforge.net/2008/barcode'>
+<source href='capture.png'>
+<index num='0'>
+<symbol type='QR-Code' quality='1' orientation='UP'><data><![CDATA[WIFI:S:Office;T:WPA;P:a]]]]><![CDATA[>b;;]]></data></symbol>
+<symbol type='EAN-13' quality='230' orientation='UP'><data><![CDATA[4006381333931]]></data></symbol>
+<symbol type='QR-Code' quality='1' orientation='UP'><data format='base64' length='5'><![CDATA[
+aGVsbG8=
+]]></data></symbol>
+</index>
+</source>
+</barcodes>
+";
+
+    #[test]
+    pub fn test_parse_xml() {
+        let code = |symbology: &str, content: &str| DecodedCode {
+            symbology: symbology.into(),
+            content: content.into(),
+        };
+        assert_eq!(
+            parse_xml(XML),
+            vec![
+                code("QR-Code", "WIFI:S:Office;T:WPA;P:a]]>b;;"),
+                code("EAN-13", "4006381333931"),
+                code("QR-Code", "hello"),
+            ]
+        );
+
+        assert!(code("QR-Code", "https://example.com/setup").url().is_some());
+        assert!(code("QR-Code", "otpauth://totp/ShareShot?secret=ABC")
+            .url()
+            .is_none());
+        assert!(code("EAN-13", "4006381333931").url().is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires zbarimg"]
+    pub async fn test_decode_generated_codes() {
+        let contents = [
+            "https://example.com/setup",
+            "WIFI:S:Office;T:WPA;P:correct horse;;",
+            "otpauth://totp/ShareShot:jane?secret=JBSWY3DPEHPK3PXP&issuer=ShareShot",
+        ];
+        for (index, content) in contents.iter().enumerate() {
+            // A white quiet zone of four modules around the code, each module 8x8 pixels
+            let code = QrCode::new(content).unwrap();
+            let width = code.width() as u32;
+            let modules = image::GrayImage::from_fn(width + 8, width + 8, |x, y| {
+                let inside = (4..width + 4).contains(&x) && (4..width + 4).contains(&y);
+                match inside && code[((x - 4) as usize, (y - 4) as usize)] == Color::Dark {
+                    true => Luma([0]),
+                    false => Luma([255]),
+                }
+            });
+            let image = imageops::resize(
+                &modules,
+                modules.width() * 8,
+                modules.height() * 8,
+                imageops::FilterType::Nearest,
+            );
+            let path = std::env::temp_dir().join(format!("shareshot-code-{index}.png"));
+            image.save(&path).unwrap();
+
+            let codes = decode_codes(&path).await;
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(
+                codes.unwrap(),
+                vec![DecodedCode {
+                    symbology: "QR-Code".into(),
+                    content: content.to_string(),
+                }]
+            );
+        }
+    }
+}
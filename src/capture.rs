@@ -1,28 +1,356 @@
+use std::collections::BTreeMap;
+
 use arboard::Clipboard;
-use ashpd::desktop::screenshot::Screenshot;
+use ashpd::desktop::{
+    file_chooser::SelectedFiles, open_uri::OpenFileRequest, screenshot::Screenshot,
+};
+use clap::ValueEnum;
+use enum_ordinalize::Ordinalize;
+
+use crate::{
+    annotation::EditorAction,
+    application::{editor::edit_capture, CONFIG},
+    barcode::{decode_codes, DecodedCode},
+    config::{AutoRedactConfig, RedactionStyle},
+    dbus::notification::{notify, notify_with_action},
+    error::Error,
+    history::{append_history, HistoryEntry},
+    ocr::{find_sensitive_text, recognize_text, recognize_words},
+    payload::Payload,
+    processing::{redact::redact_payload, Region},
+    upload::{upload_payload, UploadResult},
+};
+
+/// What is done with a screen capture.
+#[derive(Debug, ValueEnum, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "lowercase")]
+pub enum CaptureMode {
+    /// Uploads the capture
+    Upload,
+    /// Copies the text of the capture recognized using OCR
+    Text,
+    /// Copies the content of the QR codes and barcodes of the capture, without uploading it
+    Code,
+}
+
+impl Default for CaptureMode {
+    fn default() -> Self {
+        Self::Upload
+    }
+}
 
-use crate::{application::CONFIG, error::Error, image::Image, upload::upload_image};
+/// Options which change how a capture is processed.
+#[derive(Debug, Default, Clone)]
+pub struct CaptureOptions {
+    // Uploads the payload even if it was already uploaded before
+    pub force_upload: bool,
+    // Regions of the screenshot which are redacted before anything else
+    pub redact: Vec<Region>,
+}
 
 /// Makes a screen capture and uploads it to the server defined in the configuration.
-pub async fn capture_and_upload() -> Result<(), Error> {
-    let image = make_screen_capture().await?;
-    let url = upload_image(&image).await?;
+///
+/// The screenshot is redacted first, then, if the annotation editor is enabled, edited and
+/// possibly saved locally or discarded instead of being uploaded.
+///
+/// # Returns
+/// The result of the upload, including the profile which received the payload, or `None` if
+/// the screenshot was not uploaded
+pub async fn capture_and_upload(options: &CaptureOptions) -> Result<Option<UploadResult>, Error> {
+    let capture = make_screen_capture().await?;
+    let (annotate, redaction, auto_redact) = {
+        let config = CONFIG.lock().await;
+        (
+            config.annotate,
+            config.redaction,
+            config.auto_redact.clone(),
+        )
+    };
+
+    let (redacted, redactions) =
+        redact_capture(&capture, options, redaction, auto_redact.as_ref()).await?;
+    let action = match annotate {
+        true => edit_capture(redacted, redaction).await?,
+        false => EditorAction::Upload(redacted),
+    };
+    let result = match action {
+        EditorAction::Upload(payload) => {
+            Some(share_payload(&payload, options, "Screenshot uploaded", &redactions).await?)
+        }
+        EditorAction::Save(payload) => {
+            let mut body = save_edited_capture(&payload)?;
+            if let Some(summary) = redaction_summary(&redactions) {
+                body = format!("{body}\n{summary}");
+            }
+            if let Err(err) = notify("Screenshot saved", &body).await {
+                log::warn!("Failed to show notification: {err}");
+            }
+            None
+        }
+        EditorAction::Cancel => None,
+    };
 
-    Clipboard::new()?.set_text(url)?;
+    let config = CONFIG.lock().await;
+    if config.cleanup {
+        std::fs::remove_file(capture.path()).map_err(|err| Error::from(err))?;
+    }
+
+    Ok(result)
+}
+
+/// Makes a screen capture and copies the text recognized using OCR to the clipboard.
+///
+/// If enabled, the screenshot is uploaded as well and both the text and the link are copied.
+///
+/// # Returns
+/// The recognized text
+pub async fn capture_text(options: &CaptureOptions) -> Result<String, Error> {
+    let capture = make_screen_capture().await?;
+    let (ocr, redaction, auto_redact) = {
+        let config = CONFIG.lock().await;
+        (
+            config.ocr.clone(),
+            config.redaction,
+            config.auto_redact.clone(),
+        )
+    };
+
+    let text = recognize_text(&capture, &ocr.language).await?;
+    let result = match ocr.upload {
+        true => {
+            let (redacted, redactions) =
+                redact_capture(&capture, options, redaction, auto_redact.as_ref()).await?;
+            Some(upload_and_record(&redacted, options, &redactions).await?)
+        }
+        false => None,
+    };
+    let body = match &result {
+        Some(result) => format!(
+            "Uploaded to '{}', the text and link were copied to your clipboard",
+            result.profile()
+        ),
+        None => "The text was copied to your clipboard".to_string(),
+    };
+    let url = result.as_ref().map(|result| result.shared_url().as_str());
+    Clipboard::new()?.set_text(text_clipboard(&text, url))?;
+    if let Err(err) = notify("Text recognized", &body).await {
+        log::warn!("Failed to show notification: {err}");
+    }
 
     let config = CONFIG.lock().await;
     if config.cleanup {
-        std::fs::remove_file(image.path()).map_err(|err| Error::from(err))?;
+        std::fs::remove_file(capture.path()).map_err(|err| Error::from(err))?;
+    }
+
+    Ok(text)
+}
+
+/// Composes the clipboard content of a text capture, the link follows the text if it was uploaded.
+fn text_clipboard(text: &str, url: Option<&str>) -> String {
+    match url {
+        Some(url) => format!("{text}\n\n{url}"),
+        None => text.to_string(),
+    }
+}
+
+/// Makes a screen capture and copies the content of its QR codes and barcodes to the clipboard.
+///
+/// The capture is never uploaded. If a code contains a link, the notification offers to open it.
+///
+/// # Returns
+/// The decoded codes, an error if the capture contains none
+pub async fn capture_code() -> Result<Vec<DecodedCode>, Error> {
+    let capture = make_screen_capture().await?;
+    let codes = decode_codes(capture.path()).await;
+
+    // The capture is removed even if it contains no codes
+    if CONFIG.lock().await.cleanup {
+        std::fs::remove_file(capture.path()).map_err(|err| Error::from(err))?;
+    }
+
+    let codes = codes?;
+    let content = codes
+        .iter()
+        .map(|code| code.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Clipboard::new()?.set_text(content)?;
+
+    let summary = match codes.len() {
+        1 => format!("{} scanned", codes[0].symbology),
+        count => format!("{count} codes scanned"),
+    };
+    match codes.iter().find_map(|code| code.url()) {
+        Some(url) => {
+            let body = format!("Copied {url} to your clipboard");
+            // Waiting for the action must not delay the reply to the caller
+            tokio::spawn(async move {
+                match notify_with_action(&summary, &body, "Open Link").await {
+                    Ok(true) => {
+                        if let Err(err) = OpenFileRequest::default().send_uri(&url).await {
+                            log::error!("Failed to open '{url}': {err}");
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(err) => log::warn!("Failed to show notification: {err}"),
+                }
+            });
+        }
+        None => {
+            if let Err(err) = notify(&summary, "The content was copied to your clipboard").await {
+                log::warn!("Failed to show notification: {err}");
+            }
+        }
+    }
+
+    Ok(codes)
+}
+
+/// Uploads an arbitrary file to the server defined in the configuration.
+///
+/// # Returns
+/// The result of the upload, including the profile which received the payload
+pub async fn upload_file(path: &str, options: &CaptureOptions) -> Result<UploadResult, Error> {
+    let payload = Payload::read(path)?;
+    share_payload(&payload, options, "File uploaded", &BTreeMap::new()).await
+}
+
+/// Pastes text to the paste profile defined in the configuration.
+///
+/// # Returns
+/// The result of the upload, including the profile which received the paste
+pub async fn paste_text(text: String, options: &CaptureOptions) -> Result<UploadResult, Error> {
+    let payload = Payload::text(text);
+    share_payload(&payload, options, "Text pasted", &BTreeMap::new()).await
+}
+
+/// Pastes the current clipboard text to the paste profile defined in the configuration.
+///
+/// # Returns
+/// The result of the upload, including the profile which received the paste
+pub async fn paste_clipboard(options: &CaptureOptions) -> Result<UploadResult, Error> {
+    let text = Clipboard::new()?.get_text()?;
+    paste_text(text, options).await
+}
+
+/// Redacts the regions given in the options and all sensitive text found using OCR.
+///
+/// # Returns
+/// The redacted capture and the number of automatic redactions per pattern
+async fn redact_capture(
+    capture: &Payload,
+    options: &CaptureOptions,
+    style: RedactionStyle,
+    auto_redact: Option<&AutoRedactConfig>,
+) -> Result<(Payload, BTreeMap<String, usize>), Error> {
+    let mut regions = options.redact.clone();
+    let mut redactions = BTreeMap::new();
+    if let Some(config) = auto_redact {
+        let words = recognize_words(capture, &config.language).await?;
+        for (name, region) in find_sensitive_text(&words, &config.patterns, config.padding)? {
+            *redactions.entry(name).or_insert(0) += 1;
+            regions.push(region);
+        }
+    }
+
+    match regions.is_empty() {
+        true => Ok((capture.clone(), redactions)),
+        false => Ok((redact_payload(capture, &regions, style)?, redactions)),
+    }
+}
+
+/// Describes the automatic redactions, e.g. `Redacted email (2), iban (1)`.
+fn redaction_summary(redactions: &BTreeMap<String, usize>) -> Option<String> {
+    if redactions.is_empty() {
+        return None;
+    }
+    let redactions = redactions
+        .iter()
+        .map(|(name, count)| format!("{name} ({count})"))
+        .collect::<Vec<_>>();
+    Some(format!("Redacted {}", redactions.join(", ")))
+}
+
+/// Uploads a payload, copies the resulting url to the clipboard and records it in the history.
+///
+/// # Returns
+/// The result of the upload
+async fn share_payload(
+    payload: &Payload,
+    options: &CaptureOptions,
+    summary: &str,
+    redactions: &BTreeMap<String, usize>,
+) -> Result<UploadResult, Error> {
+    let result = upload_and_record(payload, options, redactions).await?;
+
+    Clipboard::new()?.set_text(result.shared_url())?;
+
+    let mut body = match result.local() {
+        true => format!(
+            "Encoded by '{}', the data URI was copied to your clipboard",
+            result.profile()
+        ),
+        false => format!(
+            "Uploaded to '{}', the link was copied to your clipboard",
+            result.profile()
+        ),
+    };
+    if let Some(redactions) = redaction_summary(redactions) {
+        body = format!("{body}\n{redactions}");
+    }
+    if let Err(err) = notify(summary, &body).await {
+        log::warn!("Failed to show notification: {err}");
+    }
+
+    Ok(result)
+}
+
+/// Uploads a payload and records it in the history.
+///
+/// # Returns
+/// The result of the upload
+async fn upload_and_record(
+    payload: &Payload,
+    options: &CaptureOptions,
+    redactions: &BTreeMap<String, usize>,
+) -> Result<UploadResult, Error> {
+    let result = upload_payload(payload, options.force_upload).await?;
+
+    // Local urls like data URIs are too large for the history and can't be reused
+    if !result.reused() && !result.local() {
+        let mut entry = HistoryEntry::new(result.profile().clone(), result.url().clone());
+        entry.short_url = result.short_url().clone();
+        entry.hash = Some(payload.hash());
+        entry.saved_bytes = result.saved_bytes();
+        entry.redactions = redactions.clone();
+        if let Err(err) = append_history(&entry) {
+            log::warn!("Failed to add upload to history: {err}");
+        }
     }
 
-    Ok(())
+    Ok(result)
+}
+
+/// Writes an edited screenshot next to the original, e.g. `Screenshot-edited.png`.
+///
+/// # Returns
+/// The path of the saved screenshot
+fn save_edited_capture(payload: &Payload) -> Result<String, Error> {
+    let stem = payload
+        .path()
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let path = payload.path().with_file_name(format!("{stem}-edited.png"));
+    std::fs::write(&path, payload.bytes()).map_err(|err| Error::from(err))?;
+    Ok(path.display().to_string())
 }
 
 /// Requests the screen capture using xdg-desktop-portal.
 ///
 /// # Returns
 /// The path to the screenshot file
-pub async fn make_screen_capture() -> Result<Image, Error> {
+pub async fn make_screen_capture() -> Result<Payload, Error> {
     let mut path: String = Screenshot::request()
         .interactive(true)
         .send()
@@ -36,5 +364,46 @@ pub async fn make_screen_capture() -> Result<Image, Error> {
     path = urlencoding::decode(path.as_str())
         .map_err(|err| Error::from(err))?
         .to_string();
-    Image::read(path)
+
+    if !std::path::Path::new(&path).exists() {
+        return Err(Error::ImageNotFound);
+    }
+    Payload::read(path)
+}
+
+/// Lets the user select a file to upload using xdg-desktop-portal.
+///
+/// # Returns
+/// The path to the selected file
+pub async fn select_file() -> Result<String, Error> {
+    let uri = SelectedFiles::open_file()
+        .title("Upload File")
+        .modal(false)
+        .multiple(false)
+        .send()
+        .await
+        .map_err(|err| Error::from(err))?
+        .response()
+        .map_err(|err| Error::from(err))?
+        .uris()
+        .first()
+        .map(|uri| uri.path().to_string())
+        .ok_or(Error::FileNotFound(String::new()))?;
+    Ok(urlencoding::decode(uri.as_str())
+        .map_err(|err| Error::from(err))?
+        .to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::text_clipboard;
+
+    #[test]
+    pub fn test_text_clipboard() {
+        assert_eq!(text_clipboard("Hello\nWorld", None), "Hello\nWorld");
+        assert_eq!(
+            text_clipboard("Hello\nWorld", Some("https://example.com/a.png")),
+            "Hello\nWorld\n\nhttps://example.com/a.png"
+        );
+    }
 }
@@ -8,10 +8,18 @@ use reqwest::Method;
 use serde::{Deserialize, Serialize};
 
 /// The different strategies which can be used to upload the image data.
+///
+/// `Json` and `UrlEncodedForm` send a body template which references the base64 encoded data
+/// using `{base64}`, alongside the other template variables like `{file_name}`.
+/// `GraphQl` follows the GraphQL multipart request specification, the body template contains
+/// the variables of the mutation.
 #[derive(Debug, Serialize, Deserialize, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
 pub enum UploadStrategy {
     Body,
     Multipart,
+    Json,
+    UrlEncodedForm,
+    GraphQl,
 }
 
 #[derive(Debug, Serialize, Deserialize, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
@@ -21,26 +29,366 @@ pub enum RequestMethod {
     Put,
 }
 
-/// The configuration for the upload server.
-#[derive(Debug, Serialize, Deserialize, Default)]
-pub struct UploadConfig {
+/// The different URL shortener services which can be used to shorten uploaded image urls.
+#[derive(Debug, Serialize, Deserialize, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
+pub enum ShortenerType {
+    Yourls,
+    Shlink,
+    Kutt,
+    Template,
+}
+
+/// The configuration for the URL shortener which is applied after uploading.
+///
+/// For the `Template` type the `url`, `headers` and `body` may reference the uploaded url
+/// using `{url}` (or `{url_encoded}` for a percent-encoded version).
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ShortenerConfig {
+    #[serde(rename = "type")]
+    pub shortener_type: ShortenerType,
+    pub url: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub request_method: RequestMethod,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    // Defaults to the parser matching the shortener type
+    #[serde(default)]
+    pub url_parser: Option<String>,
+}
+
+/// A single request of a multi-step upload chain.
+///
+/// The `url`, `headers`, `form_fields` and `body` may reference values extracted from
+/// the responses of previous steps using `{name}`, as well as `{file_name}`, `{mime_type}`
+/// and `{size}` of the uploaded image.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct RequestStep {
+    pub url: String,
+    #[serde(default)]
+    pub request_method: RequestMethod,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    // Attaches the image using the given strategy, no image is sent if unset
+    #[serde(default)]
+    pub upload_strategy: Option<UploadStrategy>,
+    #[serde(default)]
+    pub file_form_name: Option<String>,
+    // Additional multipart fields, sent before the image
+    #[serde(default)]
+    pub form_fields: BTreeMap<String, String>,
+    // Request body used if no image is attached, or the body template of the
+    // `Json`, `UrlEncodedForm` and `GraphQl` strategies
+    #[serde(default)]
+    pub body: Option<String>,
+    // The mutation of the `GraphQl` strategy, has to declare the file as `$file`
+    #[serde(default)]
+    pub graphql_query: Option<String>,
+    // Url parser statements whose results can be referenced by later steps
+    #[serde(default)]
+    pub extract: BTreeMap<String, String>,
+}
+
+/// The different backends which can be used to upload images.
+#[derive(Debug, Serialize, Deserialize, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
+pub enum BackendType {
+    Http,
+    Paste,
+    DataUri,
+}
+
+/// The kinds of content an upload profile accepts.
+#[derive(Debug, Serialize, Deserialize, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    // Screenshots and other files
+    Files,
+    // Text pastes
+    Text,
+    Any,
+}
+
+/// The different paste services supported by the paste backend.
+#[derive(Debug, Serialize, Deserialize, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
+pub enum PasteService {
+    PrivateBin,
+    Hastebin,
+    NullPointer,
+}
+
+/// The image formats screenshots can be converted to before uploading.
+#[derive(Debug, Serialize, Deserialize, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    // Always lossless
+    WebP,
+    Avif,
+}
+
+/// The configuration for converting images before uploading.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ConvertConfig {
+    pub format: OutputFormat,
+    // Quality (1-100) of the lossy JPEG and AVIF formats, defaults to 80
+    #[serde(default)]
+    pub quality: Option<u8>,
+}
+
+/// The metadata which is kept when uploading images.
+#[derive(Debug, Serialize, Deserialize, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataPolicy {
+    // Removes all metadata including color profiles
+    Strip,
+    // Removes all metadata except the color profile
+    ColorProfile,
+    Keep,
+}
+
+/// The configuration for the lossless optimization of PNG images.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OptimizeConfig {
+    // Effort of the filter and deflate search (0-6), higher levels are slower
+    pub level: u8,
+    // Seconds after which the best result found so far is used
+    pub timeout: Option<u64>,
+}
+
+/// The ways redacted regions of a screenshot are hidden.
+#[derive(Debug, Serialize, Deserialize, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
+pub enum RedactionStyle {
+    Pixelate,
+    // Blurs a downscaled copy of the region, so the original pixels can't be restored
+    Blur,
+    // Fills the region with black
+    Solid,
+}
+
+/// The configuration for recognizing the text of captures.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OcrConfig {
+    // Tesseract language(s), e.g. `eng` or `eng+deu`
+    pub language: String,
+    // Uploads the capture as well, copying both the text and the link
+    pub upload: bool,
+}
+
+/// The configuration for redacting sensitive text found using OCR.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AutoRedactConfig {
+    // Tesseract language(s), e.g. `eng` or `eng+deu`
+    pub language: String,
+    // Named regular expressions, all text matching one of them is redacted
+    pub patterns: BTreeMap<String, String>,
+    // Pixels added around the recognized text
+    pub padding: u32,
+}
+
+/// The corners in which a watermark can be placed.
+#[derive(Debug, Serialize, Deserialize, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// The configuration for the watermark drawn onto images before uploading.
+///
+/// If both a logo and a text are configured, the text is placed below the logo.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WatermarkConfig {
+    // Path to the logo image
+    pub image: Option<PathBuf>,
+    // Text template, supports `{user}`, `{timestamp}`, `{date}` and the payload variables
+    pub text: Option<String>,
+    // Path to a TrueType or OpenType font, defaults to
+    // `/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf`
+    pub font: Option<PathBuf>,
+    // RGB color of the text
+    pub color: [u8; 3],
+    pub position: WatermarkPosition,
+    // Opacity of the watermark (0.0-1.0)
+    pub opacity: f32,
+    // Width of the watermark relative to the image width (0.0-1.0)
+    pub scale: f32,
+    // Distance to the edges of the image in pixels
+    pub margin: u32,
+}
+
+/// The request methods which can be used to verify uploaded urls.
+#[derive(Debug, Serialize, Deserialize, strum_macros::IntoStaticStr, Ordinalize, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyMethod {
+    Head,
+    Get,
+}
+
+/// The configuration for verifying that an uploaded url can be downloaded before it is copied.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct VerifyConfig {
+    pub request_method: VerifyMethod,
+    // Seconds in which the url has to become reachable
+    pub timeout: u64,
+    // Seconds between two verification attempts
+    pub interval: u64,
+    // Expected content type pattern like `image/*`, defaults to the type of the uploaded file,
+    // `*` accepts all types
+    pub content_type: Option<String>,
+}
+
+/// The configuration for the data URI backend, which encodes the image into the url itself
+/// instead of uploading it.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct DataUriConfig {
+    // Maximum length of the data URI, larger images are recompressed in their format and
+    // downscaled if that is not enough
+    pub max_size: Option<u64>,
+    // Quality (1-100) of lossy formats used when recompressing, defaults to 80
+    pub quality: Option<u8>,
+}
+
+/// The configuration for the paste backend.
+///
+/// Custom paste endpoints can be used with the HTTP backend and `content = "Text"` instead.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct PasteConfig {
+    pub service: PasteService,
+    // The base url of the paste service, e.g. `https://hastebin.com`
+    pub url: String,
+    pub headers: BTreeMap<String, String>,
+    // Expiration of PrivateBin pastes, e.g. `1day` or `never`
+    pub expiration: Option<String>,
+}
+
+/// The configuration for the HTTP upload backend.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct HttpConfig {
     pub url: String,
     pub request_method: RequestMethod,
     pub headers: BTreeMap<String, String>,
     pub upload_strategy: UploadStrategy,
     pub file_form_name: Option<String>,
+    // Additional multipart fields, sent before the image
+    pub form_fields: BTreeMap<String, String>,
+    // Body template of the `Json`, `UrlEncodedForm` and `GraphQl` strategies, defaults to the
+    // strategy's template
+    pub body_template: Option<String>,
+    // The mutation of the `GraphQl` strategy, has to declare the file as `$file`
+    pub graphql_query: Option<String>,
     pub url_parser: String,
+    // Replaces the single upload request, the last step's response is parsed using `url_parser`
+    pub steps: Vec<RequestStep>,
+}
+
+/// The name under which the main upload server (`upload_server`) can be referenced.
+pub const PRIMARY_PROFILE: &str = "default";
+
+/// The configuration for the upload server.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct UploadConfig {
+    // The backend used for uploading, defaults to HTTP
+    #[serde(rename = "type", default)]
+    pub backend: BackendType,
+    // Stored in the profile itself to stay compatible with older configurations
+    #[serde(flatten)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub paste: PasteConfig,
+    #[serde(default)]
+    pub data_uri: DataUriConfig,
+    // Whether this profile accepts files, text pastes or both
+    #[serde(default)]
+    pub content: ContentKind,
+    // Additional attempts made before giving up on this profile
+    #[serde(default)]
+    pub retries: u32,
+    // Name of the profile used once all attempts on this profile failed
+    #[serde(default)]
+    pub fallback: Option<String>,
+    #[serde(default)]
+    pub shortener: Option<ShortenerConfig>,
+    // Encrypts the image before uploading, the key is appended to the url as fragment
+    #[serde(default)]
+    pub encrypt: bool,
+    // Reuses the url of a previous upload of the same image to this profile
+    #[serde(default)]
+    pub deduplicate: bool,
+    // Upload speed limit in bytes per second, overrides the global limit
+    #[serde(default)]
+    pub rate_limit: Option<u64>,
+    // MIME types accepted by this profile, e.g. `image/*`, all types are accepted if empty
+    #[serde(default)]
+    pub allowed_types: Vec<String>,
+    // Maximum file size in bytes accepted by this profile, larger images are recompressed
+    // with a lower quality and downscaled until they fit
+    #[serde(default)]
+    pub max_size: Option<u64>,
+    // Maximum image width in pixels, larger images are downscaled
+    #[serde(default)]
+    pub max_width: Option<u32>,
+    // Maximum image height in pixels, larger images are downscaled
+    #[serde(default)]
+    pub max_height: Option<u32>,
+    // Converts images to another format before uploading
+    #[serde(default)]
+    pub convert: Option<ConvertConfig>,
+    // Metadata like EXIF location data or PNG text chunks kept in uploaded images
+    #[serde(default)]
+    pub metadata: MetadataPolicy,
+    // Draws a logo or text onto images before uploading
+    #[serde(default)]
+    pub watermark: Option<WatermarkConfig>,
+    // Optimizes PNG images losslessly before uploading, applied after the conversion
+    #[serde(default)]
+    pub optimize: Option<OptimizeConfig>,
+    // Checks that the uploaded url is reachable before it is copied
+    #[serde(default)]
+    pub verify: Option<VerifyConfig>,
 }
 
 /// The main configuration file
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct ShareShotConfig {
     // Deletes a screenshot after it was read
     pub cleanup: bool,
     pub upload_server: UploadConfig,
+    // Additional upload servers which can be referenced by name
+    #[serde(default)]
+    pub profiles: BTreeMap<String, UploadConfig>,
+    // Upload speed limit in bytes per second for all profiles
+    #[serde(default)]
+    pub upload_rate_limit: Option<u64>,
+    // Ignores all upload speed limits while on an unmetered network
+    #[serde(default)]
+    pub unlimited_on_unmetered: bool,
+    // Profile which receives text pastes, defaults to the main upload server
+    #[serde(default)]
+    pub paste_profile: Option<String>,
+    // Opens the annotation editor after a screenshot was taken
+    #[serde(default)]
+    pub annotate: bool,
+    // How regions are redacted, both in the editor and using `--redact`
+    #[serde(default)]
+    pub redaction: RedactionStyle,
+    // Redacts sensitive text like email addresses found using OCR before uploading captures
+    #[serde(default)]
+    pub auto_redact: Option<AutoRedactConfig>,
+    // Used when capturing text instead of uploading
+    #[serde(default)]
+    pub ocr: OcrConfig,
 }
 
-impl UploadConfig {
+impl HttpConfig {
     pub fn set_url(&mut self, url: String) {
         self.url = url;
     }
@@ -61,11 +409,29 @@ impl UploadConfig {
         self.file_form_name = Some(file_form_name)
     }
 
+    pub fn set_body_template(&mut self, body_template: Option<String>) {
+        self.body_template = body_template;
+    }
+
+    pub fn set_graphql_query(&mut self, graphql_query: Option<String>) {
+        self.graphql_query = graphql_query;
+    }
+
     pub fn set_url_parser(&mut self, url_parser: String) {
         self.url_parser = url_parser;
     }
 }
 
+impl UploadConfig {
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    pub fn set_fallback(&mut self, fallback: Option<String>) {
+        self.fallback = fallback;
+    }
+}
+
 impl ShareShotConfig {
     pub fn set_cleanup(&mut self, cleanup: bool) {
         self.cleanup = cleanup;
@@ -74,6 +440,61 @@ impl ShareShotConfig {
     pub fn save(&self) -> Result<(), Error> {
         save_config(&self)
     }
+
+    /// Looks up an upload profile by its name.
+    ///
+    /// # Returns
+    /// The profile or `None` if no profile with the given name exists
+    pub fn profile(&self, name: &str) -> Option<&UploadConfig> {
+        match name {
+            PRIMARY_PROFILE => Some(&self.upload_server),
+            _ => self.profiles.get(name),
+        }
+    }
+
+    /// Resolves the upload speed limit of a profile, ignoring whether the network is metered.
+    ///
+    /// # Returns
+    /// The limit in bytes per second or `None` if uploads are not limited
+    pub fn rate_limit(&self, profile: &UploadConfig) -> Option<u64> {
+        profile.rate_limit.or(self.upload_rate_limit)
+    }
+
+    /// Resolves the order in which profiles are tried, starting with the primary upload server
+    /// and following the configured fallbacks.
+    ///
+    /// # Returns
+    /// The names and configurations of all profiles in the fallback chain
+    pub fn fallback_chain(&self) -> Result<Vec<(&str, &UploadConfig)>, Error> {
+        self.fallback_chain_from(PRIMARY_PROFILE)
+    }
+
+    /// Resolves the order in which profiles are tried, starting with the given profile.
+    ///
+    /// # Returns
+    /// The names and configurations of all profiles in the fallback chain
+    pub fn fallback_chain_from<'a>(
+        &'a self,
+        start: &'a str,
+    ) -> Result<Vec<(&'a str, &'a UploadConfig)>, Error> {
+        let profile = self
+            .profile(start)
+            .ok_or(Error::UnknownProfile(start.to_string()))?;
+        let mut chain = vec![(start, profile)];
+
+        while let Some(name) = chain.last().and_then(|(_, profile)| profile.fallback.as_deref()) {
+            // Ignore cyclic fallbacks, every profile is only tried once
+            if chain.iter().any(|(visited, _)| *visited == name) {
+                break;
+            }
+            let profile = self
+                .profile(name)
+                .ok_or(Error::UnknownProfile(name.to_string()))?;
+            chain.push((name, profile));
+        }
+
+        Ok(chain)
+    }
 }
 
 pub trait AllEnumValues {
@@ -94,11 +515,193 @@ impl AllEnumValues for UploadStrategy {
     fn all() -> Vec<UploadStrategy> {
         vec![
             Self::Body,
-            Self::Multipart
+            Self::Multipart,
+            Self::Json,
+            Self::UrlEncodedForm,
+            Self::GraphQl,
         ]
     }
 }
 
+impl UploadStrategy {
+    /// The body template used if none is configured, only set for templated strategies.
+    pub fn default_body_template(&self) -> Option<&'static str> {
+        match self {
+            UploadStrategy::Json => Some(r#"{"image": "{base64}", "name": "{file_name}"}"#),
+            UploadStrategy::UrlEncodedForm => Some("image={base64}&name={file_name}"),
+            UploadStrategy::GraphQl => Some("{}"),
+            _ => None,
+        }
+    }
+
+    /// The content type of the request body, only set for templated strategies sending a plain body.
+    pub fn content_type(&self) -> Option<&'static str> {
+        match self {
+            UploadStrategy::Json => Some("application/json"),
+            UploadStrategy::UrlEncodedForm => Some("application/x-www-form-urlencoded"),
+            _ => None,
+        }
+    }
+}
+
+impl ShortenerType {
+    /// The url parser statement matching the response of the shortener service.
+    pub fn default_url_parser(&self) -> &'static str {
+        match self {
+            ShortenerType::Yourls => "$json:shorturl$",
+            ShortenerType::Shlink => "$json:shortUrl$",
+            ShortenerType::Kutt => "$json:link$",
+            ShortenerType::Template => "$raw$",
+        }
+    }
+}
+
+impl Default for ShortenerType {
+    fn default() -> Self {
+        Self::Template
+    }
+}
+
+impl Default for BackendType {
+    fn default() -> Self {
+        Self::Http
+    }
+}
+
+impl Default for ContentKind {
+    fn default() -> Self {
+        Self::Files
+    }
+}
+
+impl OutputFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::WebP => "image/webp",
+            OutputFormat::Avif => "image/avif",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::WebP => "webp",
+            OutputFormat::Avif => "avif",
+        }
+    }
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        Self::Png
+    }
+}
+
+impl Default for MetadataPolicy {
+    fn default() -> Self {
+        Self::Keep
+    }
+}
+
+impl Default for OptimizeConfig {
+    fn default() -> Self {
+        Self {
+            level: 2,
+            timeout: None,
+        }
+    }
+}
+
+impl Default for RedactionStyle {
+    fn default() -> Self {
+        Self::Pixelate
+    }
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            language: "eng".into(),
+            upload: false,
+        }
+    }
+}
+
+impl Default for AutoRedactConfig {
+    fn default() -> Self {
+        Self {
+            language: "eng".into(),
+            patterns: BTreeMap::from([
+                (
+                    "email".into(),
+                    r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}".into(),
+                ),
+                (
+                    "ip_address".into(),
+                    r"\b(?:(?:\d{1,3}\.){3}\d{1,3}|(?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4})\b".into(),
+                ),
+                // Stripe, AWS, GitHub and Slack keys, JSON web tokens and long hex secrets
+                (
+                    "api_key".into(),
+                    r"\b(?:[sprk]k_(?:live|test)_[A-Za-z0-9]{16,}|AKIA[0-9A-Z]{16}|gh[pousr]_[A-Za-z0-9]{36,}|xox[abprs]-[A-Za-z0-9-]{10,}|eyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}|[0-9a-fA-F]{32,})\b".into(),
+                ),
+                (
+                    "iban".into(),
+                    r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b".into(),
+                ),
+            ]),
+            padding: 4,
+        }
+    }
+}
+
+impl Default for WatermarkPosition {
+    fn default() -> Self {
+        Self::BottomRight
+    }
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            image: None,
+            text: None,
+            font: None,
+            color: [255, 255, 255],
+            position: WatermarkPosition::default(),
+            opacity: 0.5,
+            scale: 0.2,
+            margin: 16,
+        }
+    }
+}
+
+impl Default for VerifyMethod {
+    fn default() -> Self {
+        Self::Head
+    }
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self {
+            request_method: VerifyMethod::default(),
+            timeout: 10,
+            interval: 1,
+            content_type: None,
+        }
+    }
+}
+
+impl Default for PasteService {
+    fn default() -> Self {
+        Self::Hastebin
+    }
+}
+
 impl Default for UploadStrategy {
     fn default() -> Self {
         Self::Multipart
@@ -166,3 +769,63 @@ fn config_path() -> Option<PathBuf> {
     home_dir.push("config.toml");
     Some(home_dir)
 }
+
+#[cfg(test)]
+pub mod tests {
+    use crate::error::Error;
+
+    use super::{BackendType, ShareShotConfig, UploadConfig, UploadStrategy, PRIMARY_PROFILE};
+
+    #[test]
+    pub fn test_load_flat_upload_config() {
+        let config = toml::from_str::<ShareShotConfig>(
+            r#"
+            cleanup = true
+
+            [upload_server]
+            url = "https://example.com/upload"
+            request_method = "Post"
+            upload_strategy = "Body"
+            file_form_name = "file"
+            url_parser = "$json:url$"
+
+            [upload_server.headers]
+            Authorization = "secret"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.upload_server.backend, BackendType::Http);
+        assert_eq!(config.upload_server.http.url, "https://example.com/upload");
+        assert_eq!(config.upload_server.http.upload_strategy, UploadStrategy::Body);
+        assert_eq!(config.upload_server.http.headers["Authorization"], "secret");
+    }
+
+    #[test]
+    pub fn test_fallback_chain() {
+        let mut config = ShareShotConfig::default();
+        config.upload_server.fallback = Some("backup".into());
+        for (name, fallback) in [("backup", "mirror"), ("mirror", "backup")] {
+            let profile = UploadConfig {
+                fallback: Some(fallback.into()),
+                ..Default::default()
+            };
+            config.profiles.insert(name.into(), profile);
+        }
+
+        // Cyclic fallbacks end the chain instead of trying a profile twice
+        let names: Vec<&str> = config
+            .fallback_chain()
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec![PRIMARY_PROFILE, "backup", "mirror"]);
+
+        config.profiles.get_mut("mirror").unwrap().fallback = Some("missing".into());
+        assert!(matches!(
+            config.fallback_chain(),
+            Err(Error::UnknownProfile(name)) if name == "missing"
+        ));
+    }
+}
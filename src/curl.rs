@@ -0,0 +1,380 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{
+    config::{HttpConfig, RequestMethod, UploadStrategy},
+    error::Error,
+};
+
+// Options which don't change the request and can safely be ignored
+const IGNORED_FLAGS: [&str; 12] = [
+    "-s",
+    "--silent",
+    "-S",
+    "--show-error",
+    "-v",
+    "--verbose",
+    "-L",
+    "--location",
+    "-i",
+    "--include",
+    "-f",
+    "--fail",
+];
+// Short options without a value, which may be combined like `-sSL`
+const BOOLEAN_SHORT_FLAGS: [char; 7] = ['s', 'S', 'v', 'L', 'i', 'f', 'k'];
+// Options which take a value but can't be translated into an upload configuration
+const UNSUPPORTED_OPTIONS: [&str; 34] = [
+    "-o",
+    "--output",
+    "-m",
+    "--max-time",
+    "--connect-timeout",
+    "--retry",
+    "--retry-delay",
+    "--retry-max-time",
+    "-x",
+    "--proxy",
+    "-U",
+    "--proxy-user",
+    "--cacert",
+    "--capath",
+    "-E",
+    "--cert",
+    "--key",
+    "-w",
+    "--write-out",
+    "-c",
+    "--cookie-jar",
+    "-r",
+    "--range",
+    "-D",
+    "--dump-header",
+    "-K",
+    "--config",
+    "--resolve",
+    "--limit-rate",
+    "--max-filesize",
+    "--interface",
+    "--trace",
+    "--trace-ascii",
+    "--json",
+];
+// Parameters of form fields, e.g. `file=@image.png;type=image/png`
+const FORM_PARAMETERS: [&str; 4] = [";type=", ";filename=", ";headers=", ";encoder="];
+
+/// The result of importing a curl command.
+#[derive(Debug, Default)]
+pub struct CurlImport {
+    pub config: HttpConfig,
+    // Describes all parts of the command which could not be translated
+    pub warnings: Vec<String>,
+}
+
+/// Parses a curl command line (e.g. from the documentation of an upload server) into
+/// an HTTP upload configuration.
+///
+/// # Returns
+/// The configuration and warnings about curl options which could not be translated
+pub fn parse_curl_command(command: &str) -> Result<CurlImport, Error> {
+    let arguments =
+        shell_words::split(command.trim()).map_err(|err| Error::CurlImport(err.to_string()))?;
+    let mut arguments = arguments.into_iter();
+
+    match arguments.next() {
+        Some(program) if program == "curl" || program.ends_with("/curl") => {}
+        _ => {
+            return Err(Error::CurlImport(
+                "The command does not start with curl".into(),
+            ))
+        }
+    }
+
+    let mut import = CurlImport::default();
+    let mut method = None;
+    let mut upload_strategy = None;
+
+    while let Some(argument) = arguments.next() {
+        for (option, attached) in split_short_options(&argument) {
+            let mut value = |name: &str| {
+                attached
+                    .clone()
+                    .or_else(|| arguments.next())
+                    .ok_or(Error::CurlImport(format!("Missing value for {name}")))
+            };
+
+            match option.as_str() {
+                "-X" | "--request" => {
+                    let value = value(&option)?;
+                    method = match value.to_uppercase().as_str() {
+                        "GET" => Some(RequestMethod::Get),
+                        "POST" => Some(RequestMethod::Post),
+                        "PUT" => Some(RequestMethod::Put),
+                        _ => {
+                            import
+                                .warnings
+                                .push(format!("Unsupported request method {value}"));
+                            None
+                        }
+                    };
+                }
+                "-H" | "--header" => match value(&option)?.split_once(':') {
+                    Some((key, header_value)) => {
+                        import
+                            .config
+                            .headers
+                            .insert(key.trim().to_string(), header_value.trim().to_string());
+                    }
+                    None => import.warnings.push("Ignored invalid header".into()),
+                },
+                "-A" | "--user-agent" => {
+                    import
+                        .config
+                        .headers
+                        .insert("User-Agent".into(), value(&option)?);
+                }
+                "-e" | "--referer" => {
+                    import
+                        .config
+                        .headers
+                        .insert("Referer".into(), value(&option)?);
+                }
+                "-b" | "--cookie" => {
+                    import
+                        .config
+                        .headers
+                        .insert("Cookie".into(), value(&option)?);
+                }
+                "--oauth2-bearer" => {
+                    let token = value(&option)?;
+                    import
+                        .config
+                        .headers
+                        .insert("Authorization".into(), format!("Bearer {token}"));
+                }
+                "-u" | "--user" => {
+                    let credentials = STANDARD.encode(value(&option)?);
+                    import
+                        .config
+                        .headers
+                        .insert("Authorization".into(), format!("Basic {credentials}"));
+                }
+                "-F" | "--form" | "--form-string" => {
+                    let field = value(&option)?;
+                    let (name, content) = field
+                        .split_once('=')
+                        .ok_or(Error::CurlImport(format!("Invalid form field {field}")))?;
+                    let content = match option.as_str() {
+                        "--form-string" => content,
+                        _ => strip_form_parameters(content),
+                    };
+
+                    match content.strip_prefix('@') {
+                        Some(_) if option != "--form-string" => {
+                            if upload_strategy == Some(UploadStrategy::Multipart) {
+                                import
+                                    .warnings
+                                    .push(format!("Only one file can be uploaded, ignored {name}"));
+                                continue;
+                            }
+                            upload_strategy = Some(UploadStrategy::Multipart);
+                            import.config.file_form_name = Some(name.to_string());
+                        }
+                        _ => {
+                            import
+                                .config
+                                .form_fields
+                                .insert(name.to_string(), content.to_string());
+                        }
+                    }
+                }
+                "-d" | "--data" | "--data-ascii" | "--data-binary" | "--data-raw"
+                | "--data-urlencode" => {
+                    let data = value(&option)?;
+                    match data.strip_prefix('@') {
+                        Some(_) if option != "--data-raw" && option != "--data-urlencode" => {
+                            upload_strategy = Some(UploadStrategy::Body);
+                        }
+                        _ => import.warnings.push(format!(
+                            "Ignored request body {data}, only files are supported"
+                        )),
+                    }
+                }
+                "-T" | "--upload-file" => {
+                    value(&option)?;
+                    upload_strategy = Some(UploadStrategy::Body);
+                    method = method.or(Some(RequestMethod::Put));
+                }
+                "--url" => {
+                    let url = value(&option)?;
+                    set_url(&mut import, url);
+                }
+                "-k" | "--insecure" => import
+                    .warnings
+                    .push("Certificate verification can't be disabled".into()),
+                flag if IGNORED_FLAGS.contains(&flag) => {}
+                unsupported if UNSUPPORTED_OPTIONS.contains(&unsupported) => {
+                    value(&option)?;
+                    import
+                        .warnings
+                        .push(format!("Ignored unsupported option {unsupported}"));
+                }
+                // Unknown options may take a value, which is then ignored as an additional url
+                unknown if unknown.starts_with('-') => import
+                    .warnings
+                    .push(format!("Ignored unknown option {unknown}")),
+                url => set_url(&mut import, url.to_string()),
+            }
+        }
+    }
+
+    if import.config.url.is_empty() {
+        return Err(Error::CurlImport(
+            "The command does not contain a url".into(),
+        ));
+    }
+    if upload_strategy.is_none() {
+        import
+            .warnings
+            .push("The command does not upload a file, using multipart upload".into());
+    }
+
+    // curl uses POST when sending data without an explicit method
+    import.config.request_method = method.unwrap_or(match upload_strategy {
+        Some(_) => RequestMethod::Post,
+        None => RequestMethod::Get,
+    });
+    import.config.upload_strategy = upload_strategy.unwrap_or_default();
+    import.config.url_parser = "$raw$".into();
+    import
+        .warnings
+        .push("The response format is unknown, check the response parse pattern".into());
+
+    Ok(import)
+}
+
+/// Splits combined short options like `-sSL` or `-XPOST` into single options, attaching the
+/// rest of the argument to the first option which takes a value.
+fn split_short_options(argument: &str) -> Vec<(String, Option<String>)> {
+    let cluster = match argument.strip_prefix('-') {
+        Some(cluster) if !cluster.starts_with('-') && cluster.chars().count() > 1 => cluster,
+        _ => return vec![(argument.to_string(), None)],
+    };
+
+    let mut options = Vec::new();
+    for (index, flag) in cluster.char_indices() {
+        if BOOLEAN_SHORT_FLAGS.contains(&flag) {
+            options.push((format!("-{flag}"), None));
+            continue;
+        }
+        let attached = &cluster[index + flag.len_utf8()..];
+        options.push((
+            format!("-{flag}"),
+            (!attached.is_empty()).then(|| attached.to_string()),
+        ));
+        break;
+    }
+    options
+}
+
+/// Strips parameters like `;type=image/png` from the content of a form field.
+fn strip_form_parameters(content: &str) -> &str {
+    FORM_PARAMETERS
+        .iter()
+        .filter_map(|parameter| content.find(parameter))
+        .min()
+        .map_or(content, |index| &content[..index])
+}
+
+/// Sets the url of the import, commands with multiple urls upload to the first one.
+fn set_url(import: &mut CurlImport, url: String) {
+    match import.config.url.is_empty() {
+        true => import.config.url = url,
+        false => import
+            .warnings
+            .push(format!("Ignored additional url {url}")),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::config::{RequestMethod, UploadStrategy};
+
+    use super::parse_curl_command;
+
+    #[test]
+    pub fn test_parse_multipart() {
+        let import = parse_curl_command(
+            "curl -s -F 'file=@image.png' -F expires=24 \\\n  -H 'Authorization: Bearer abc' https://example.com/upload",
+        )
+        .unwrap();
+
+        assert_eq!(import.config.url, "https://example.com/upload");
+        assert_eq!(import.config.request_method, RequestMethod::Post);
+        assert_eq!(import.config.upload_strategy, UploadStrategy::Multipart);
+        assert_eq!(import.config.file_form_name, Some("file".into()));
+        assert_eq!(import.config.form_fields["expires"], "24");
+        assert_eq!(import.config.headers["Authorization"], "Bearer abc");
+    }
+
+    #[test]
+    pub fn test_parse_upload_file() {
+        let import =
+            parse_curl_command("curl -k -T image.png --proxy localhost:8080 https://example.com")
+                .unwrap();
+
+        assert_eq!(import.config.request_method, RequestMethod::Put);
+        assert_eq!(import.config.upload_strategy, UploadStrategy::Body);
+        assert!(import
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("--proxy")));
+        assert!(import
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("Certificate")));
+    }
+
+    #[test]
+    pub fn test_parse_combined_options() {
+        let import = parse_curl_command(
+            "curl -sSL -XPUT -sk -é --data-binary @image.png https://example.com/upload --oauth2-bearer abc --data-urlencode a=b",
+        )
+        .unwrap();
+
+        assert_eq!(import.config.url, "https://example.com/upload");
+        assert_eq!(import.config.request_method, RequestMethod::Put);
+        assert_eq!(import.config.upload_strategy, UploadStrategy::Body);
+        assert_eq!(import.config.headers["Authorization"], "Bearer abc");
+        assert!(import
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("Certificate")));
+        assert!(import.warnings.iter().any(|warning| warning.contains("-é")));
+        assert!(import
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("a=b")));
+
+        // Values of unknown options never replace the url
+        let import =
+            parse_curl_command("curl https://example.com/upload --unknown value -T image.png")
+                .unwrap();
+        assert_eq!(import.config.url, "https://example.com/upload");
+        assert!(import
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("value")));
+    }
+
+    #[test]
+    pub fn test_parse_form_parameters() {
+        let import = parse_curl_command(
+            "curl -F 'file=@shot.png;type=image/png' -F 'name=value;type=text/plain' --form-string 'raw=a;type=b' https://example.com",
+        )
+        .unwrap();
+
+        assert_eq!(import.config.file_form_name, Some("file".into()));
+        assert_eq!(import.config.form_fields["name"], "value");
+        assert_eq!(import.config.form_fields["raw"], "a;type=b");
+    }
+}
@@ -1,6 +1,8 @@
 use zbus::{proxy, Connection};
 
-use crate::error::Error;
+use crate::{capture::CaptureMode, error::Error, processing::Region};
+
+use super::service::CaptureRequest;
 
 #[proxy(
     interface = "dev.lennoxlotl.ShareShot.CaptureService",
@@ -9,13 +11,48 @@ use crate::error::Error;
 )]
 pub trait CaptureService {
     async fn request_capture(&self) -> zbus::Result<String>;
+    async fn request_capture_with_options(&self, request: CaptureRequest) -> zbus::Result<String>;
+    async fn upload_file(&self, path: &str, force_upload: bool) -> zbus::Result<String>;
+    async fn paste_text(&self, text: &str, force_upload: bool) -> zbus::Result<String>;
+}
+
+/// Requests a capture by invoking the dbus service, redacting the given regions.
+pub async fn request_capture(
+    mode: CaptureMode,
+    force_upload: bool,
+    redact: &[Region],
+) -> Result<(), Error> {
+    let connection = Connection::session().await?;
+    let proxy = CaptureServiceProxy::new(&connection).await?;
+    let request = CaptureRequest {
+        mode: Some(<&str>::from(mode).into()),
+        force_upload: Some(force_upload),
+        redact: Some(
+            redact
+                .iter()
+                .map(|region| (region.x, region.y, region.width, region.height))
+                .collect(),
+        ),
+    };
+    let reply = proxy.request_capture_with_options(request).await?;
+    log::info!("dbus daemon returned: {reply}");
+    Ok(())
+}
+
+/// Requests the upload of a file by invoking the dbus service.
+pub async fn request_upload(path: &str, force_upload: bool) -> Result<(), Error> {
+    let connection = Connection::session().await?;
+    let proxy = CaptureServiceProxy::new(&connection).await?;
+    let reply = proxy.upload_file(path, force_upload).await?;
+    log::info!("dbus daemon returned: {reply}");
+    Ok(())
 }
 
-/// Requests a capture by invoking the dbus service.
-pub async fn request_capture() -> Result<(), Error> {
+/// Requests a text paste by invoking the dbus service.
+pub async fn request_paste(text: &str, force_upload: bool) -> Result<(), Error> {
     let connection = Connection::session().await?;
     let proxy = CaptureServiceProxy::new(&connection).await?;
-    let reply = proxy.request_capture().await?;
+    let reply = proxy.paste_text(text, force_upload).await?;
     log::info!("dbus daemon returned: {reply}");
     Ok(())
 }
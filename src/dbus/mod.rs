@@ -1,2 +1,4 @@
 pub mod client;
+pub mod network;
+pub mod notification;
 pub mod service;
@@ -0,0 +1,41 @@
+use zbus::{proxy, Connection};
+
+use crate::error::Error;
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+pub trait NetworkManager {
+    #[zbus(property)]
+    fn metered(&self) -> zbus::Result<u32>;
+}
+
+/// Checks whether NetworkManager reports the current connection as unmetered.
+///
+/// Connections with an unknown metered state are not considered unmetered.
+pub async fn is_unmetered() -> Result<bool, Error> {
+    let connection = Connection::system().await?;
+    let proxy = NetworkManagerProxy::new(&connection).await?;
+    Ok(is_unmetered_state(proxy.metered().await?))
+}
+
+/// Checks whether a NetworkManager metered state is unmetered.
+fn is_unmetered_state(metered: u32) -> bool {
+    // See NMMetered, 2 = NM_METERED_NO and 4 = NM_METERED_GUESS_NO
+    matches!(metered, 2 | 4)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::is_unmetered_state;
+
+    #[test]
+    pub fn test_is_unmetered_state() {
+        // Unknown, yes, no, guess yes, guess no
+        let states: Vec<bool> = (0..=4).map(is_unmetered_state).collect();
+        assert_eq!(states, vec![false, false, true, false, true]);
+        assert!(!is_unmetered_state(5));
+    }
+}
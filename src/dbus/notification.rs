@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use futures_util::StreamExt;
+use zbus::{proxy, zvariant::Value, Connection};
+
+use crate::error::Error;
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+pub trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, &Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+}
+
+/// Shows a desktop notification using the notification daemon.
+pub async fn notify(summary: &str, body: &str) -> Result<(), Error> {
+    let connection = Connection::session().await?;
+    let proxy = NotificationsProxy::new(&connection).await?;
+    proxy
+        .notify("ShareShot", 0, "", summary, body, &[], HashMap::new(), -1)
+        .await?;
+    Ok(())
+}
+
+/// Shows a desktop notification offering a single action, e.g. to open a link.
+///
+/// # Returns
+/// Whether the action was invoked before the notification was closed
+pub async fn notify_with_action(summary: &str, body: &str, action: &str) -> Result<bool, Error> {
+    let connection = Connection::session().await?;
+    let proxy = NotificationsProxy::new(&connection).await?;
+    // Subscribed before showing the notification so no signal is missed
+    let mut invoked = proxy.receive_action_invoked().await?;
+    let mut closed = proxy.receive_notification_closed().await?;
+    let id = proxy
+        .notify(
+            "ShareShot",
+            0,
+            "",
+            summary,
+            body,
+            &["default", action],
+            HashMap::new(),
+            -1,
+        )
+        .await?;
+
+    loop {
+        tokio::select! {
+            Some(signal) = invoked.next() => {
+                if signal.args()?.id == id {
+                    return Ok(true);
+                }
+            }
+            Some(signal) = closed.next() => {
+                if signal.args()?.id == id {
+                    return Ok(false);
+                }
+            }
+            else => return Ok(false),
+        }
+    }
+}
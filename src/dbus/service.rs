@@ -1,6 +1,65 @@
-use zbus::{connection, interface, Connection};
+use enum_ordinalize::Ordinalize;
+use zbus::{
+    connection, interface,
+    zvariant::{DeserializeDict, SerializeDict, Type},
+    Connection,
+};
 
-use crate::{capture::capture_and_upload, error::Error};
+use crate::{
+    capture::{
+        capture_and_upload, capture_code, capture_text, paste_text, upload_file, CaptureMode,
+        CaptureOptions,
+    },
+    error::Error,
+    processing::Region,
+};
+
+/// The options of a capture requested over dbus, sent as `a{sv}` dictionary so new options don't
+/// change the signature of the method.
+#[derive(Debug, Default, DeserializeDict, SerializeDict, Type)]
+#[zvariant(signature = "a{sv}")]
+pub struct CaptureRequest {
+    // One of `upload`, `text` or `code`, defaults to `upload`
+    pub mode: Option<String>,
+    pub force_upload: Option<bool>,
+    // Regions as `(x, y, width, height)`
+    pub redact: Option<Vec<(u32, u32, u32, u32)>>,
+}
+
+impl CaptureRequest {
+    /// Parses the capture mode.
+    ///
+    /// # Returns
+    /// The requested mode or an error message for unknown modes
+    pub fn mode(&self) -> Result<CaptureMode, String> {
+        let Some(mode) = &self.mode else {
+            return Ok(CaptureMode::default());
+        };
+        CaptureMode::VARIANTS
+            .iter()
+            .copied()
+            .find(|variant| <&str>::from(variant) == mode)
+            .ok_or(format!("Unknown capture mode '{mode}'"))
+    }
+
+    /// Maps the dictionary to the options of the capture.
+    pub fn capture_options(&self) -> CaptureOptions {
+        CaptureOptions {
+            force_upload: self.force_upload.unwrap_or_default(),
+            redact: self
+                .redact
+                .iter()
+                .flatten()
+                .map(|&(x, y, width, height)| Region {
+                    x,
+                    y,
+                    width,
+                    height,
+                })
+                .collect(),
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct CaptureService;
@@ -8,11 +67,75 @@ pub struct CaptureService;
 #[interface(name = "dev.lennoxlotl.ShareShot.CaptureService")]
 impl CaptureService {
     async fn request_capture(&mut self) -> String {
-        match capture_and_upload().await {
-            Ok(_) => "Upload successful".into(),
+        self.request_capture_with_options(CaptureRequest::default())
+            .await
+    }
+
+    async fn request_capture_with_options(&mut self, request: CaptureRequest) -> String {
+        let mode = match request.mode() {
+            Ok(mode) => mode,
+            Err(err) => return format!("Invalid capture options: {err}"),
+        };
+        let options = request.capture_options();
+
+        match mode {
+            CaptureMode::Upload => match capture_and_upload(&options).await {
+                Ok(Some(result)) => format!(
+                    "Upload successful to '{}': {}",
+                    result.profile(),
+                    result.shared_url()
+                ),
+                Ok(None) => "The screenshot was not uploaded".into(),
+                Err(err) => format!("Failed to upload: {}", err),
+            },
+            CaptureMode::Text => match capture_text(&options).await {
+                Ok(text) => format!("Recognized text copied to clipboard: {text}"),
+                Err(err) => format!("Failed to recognize text: {}", err),
+            },
+            CaptureMode::Code => match capture_code().await {
+                Ok(codes) => format!(
+                    "Scanned {} code(s), content copied to clipboard: {}",
+                    codes.len(),
+                    codes
+                        .iter()
+                        .map(|code| code.content.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                Err(err) => format!("Failed to scan code: {}", err),
+            },
+        }
+    }
+
+    async fn upload_file(&mut self, path: String, force_upload: bool) -> String {
+        let options = CaptureOptions {
+            force_upload,
+            ..Default::default()
+        };
+        match upload_file(&path, &options).await {
+            Ok(result) => format!(
+                "Upload successful to '{}': {}",
+                result.profile(),
+                result.shared_url()
+            ),
             Err(err) => format!("Failed to upload: {}", err),
         }
     }
+
+    async fn paste_text(&mut self, text: String, force_upload: bool) -> String {
+        let options = CaptureOptions {
+            force_upload,
+            ..Default::default()
+        };
+        match paste_text(text, &options).await {
+            Ok(result) => format!(
+                "Paste successful to '{}': {}",
+                result.profile(),
+                result.shared_url()
+            ),
+            Err(err) => format!("Failed to paste: {}", err),
+        }
+    }
 }
 
 pub async fn create_dbus_service() -> Result<Connection, Error> {
@@ -24,3 +147,46 @@ pub async fn create_dbus_service() -> Result<Connection, Error> {
         .await
         .map_err(|err| Error::from(err))
 }
+
+#[cfg(test)]
+pub mod tests {
+    use zbus::zvariant::{serialized::Context, to_bytes, Type, LE};
+
+    use crate::{capture::CaptureMode, processing::Region};
+
+    use super::CaptureRequest;
+
+    #[test]
+    pub fn test_capture_request() {
+        assert_eq!(CaptureRequest::SIGNATURE, "a{sv}");
+
+        let request = CaptureRequest {
+            mode: Some("text".into()),
+            force_upload: None,
+            redact: Some(vec![(1, 2, 3, 4)]),
+        };
+        let bytes = to_bytes(Context::new_dbus(LE, 0), &request).unwrap();
+        let (request, _) = bytes.deserialize::<CaptureRequest>().unwrap();
+
+        assert_eq!(request.mode(), Ok(CaptureMode::Text));
+        let options = request.capture_options();
+        assert!(!options.force_upload);
+        assert_eq!(
+            options.redact,
+            vec![Region {
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 4,
+            }]
+        );
+
+        // Missing options use their defaults, unknown modes are rejected
+        assert_eq!(CaptureRequest::default().mode(), Ok(CaptureMode::Upload));
+        let request = CaptureRequest {
+            mode: Some("video".into()),
+            ..Default::default()
+        };
+        assert!(request.mode().is_err());
+    }
+}
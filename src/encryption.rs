@@ -0,0 +1,183 @@
+//! Client-side encryption of uploaded images.
+//!
+//! Encrypted uploads use the following versioned format:
+//!
+//! | Offset | Size | Content                                   |
+//! |--------|------|-------------------------------------------|
+//! | 0      | 4    | Magic bytes `SSEC`                        |
+//! | 4      | 1    | Format version (currently `1`)            |
+//! | 5      | 12   | Random AES-GCM nonce                      |
+//! | 17     | ...  | AES-256-GCM ciphertext including the tag  |
+//!
+//! The first 5 bytes (magic and version) are authenticated as associated data.
+//! The plaintext consists of the length of the MIME type as big endian `u16`, the MIME type
+//! itself and the file bytes.
+//!
+//! The random 256-bit key is never sent to the server, it is appended to the resulting url
+//! as base64url encoded (unpadded) fragment, e.g. `https://example.com/abc#<key>`.
+
+use std::path::PathBuf;
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload as AeadPayload},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+use crate::{error::Error, payload::Payload, upload::CLIENT};
+
+const MAGIC: &[u8; 4] = b"SSEC";
+const VERSION: u8 = 1;
+const HEADER_LENGTH: usize = MAGIC.len() + 1;
+const NONCE_LENGTH: usize = 12;
+
+/// Encrypts a payload using a newly generated random key.
+///
+/// # Returns
+/// The encrypted payload and the base64url encoded key
+pub fn encrypt_payload(payload: &Payload) -> Result<(Payload, String), Error> {
+    let key = Aes256Gcm::generate_key(OsRng);
+    let data = encrypt(payload.mime_type(), payload.bytes(), &key)?;
+
+    Ok((
+        Payload::new(
+            payload.path().with_extension("enc"),
+            data,
+            "application/octet-stream".into(),
+        ),
+        URL_SAFE_NO_PAD.encode(key),
+    ))
+}
+
+/// Encrypts data including its MIME type using the given key.
+fn encrypt(mime_type: &str, bytes: &[u8], key: &Key<Aes256Gcm>) -> Result<Vec<u8>, Error> {
+    let mime_length = u16::try_from(mime_type.len()).map_err(|_| Error::Encryption)?;
+    let mut plaintext = Vec::with_capacity(2 + mime_type.len() + bytes.len());
+    plaintext.extend_from_slice(&mime_length.to_be_bytes());
+    plaintext.extend_from_slice(mime_type.as_bytes());
+    plaintext.extend_from_slice(bytes);
+
+    let mut header = MAGIC.to_vec();
+    header.push(VERSION);
+
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = Aes256Gcm::new(key)
+        .encrypt(
+            &nonce,
+            AeadPayload {
+                msg: &plaintext,
+                aad: &header,
+            },
+        )
+        .map_err(|_| Error::Encryption)?;
+
+    let mut data = header;
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+/// Decrypts data encrypted by shareshot using the base64url encoded key.
+///
+/// # Returns
+/// The MIME type and the decrypted bytes
+pub fn decrypt(data: &[u8], key: &str) -> Result<(String, Vec<u8>), Error> {
+    let key = URL_SAFE_NO_PAD
+        .decode(key)
+        .ok()
+        .filter(|key| key.len() == 32)
+        .ok_or(Error::Decryption("Invalid key".into()))?;
+
+    if data.len() < HEADER_LENGTH + NONCE_LENGTH || &data[..MAGIC.len()] != MAGIC {
+        return Err(Error::Decryption("Not an encrypted shareshot upload".into()));
+    }
+    if data[MAGIC.len()] != VERSION {
+        return Err(Error::Decryption(format!(
+            "Unsupported format version {}",
+            data[MAGIC.len()]
+        )));
+    }
+
+    let (header, rest) = data.split_at(HEADER_LENGTH);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+    let plaintext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            AeadPayload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| Error::Decryption("Wrong key or corrupted data".into()))?;
+
+    if plaintext.len() < 2 {
+        return Err(Error::Decryption("Missing MIME type".into()));
+    }
+    let mime_length = u16::from_be_bytes([plaintext[0], plaintext[1]]) as usize;
+    let mime_type = plaintext
+        .get(2..2 + mime_length)
+        .and_then(|mime_type| String::from_utf8(mime_type.to_vec()).ok())
+        .ok_or(Error::Decryption("Invalid MIME type".into()))?;
+
+    Ok((mime_type, plaintext[2 + mime_length..].to_vec()))
+}
+
+/// Downloads and decrypts an encrypted upload, the key is read from the url fragment.
+///
+/// # Returns
+/// The path the decrypted file was written to
+pub async fn download_and_decrypt(url: &str, output: Option<PathBuf>) -> Result<PathBuf, Error> {
+    let (url, key) = url
+        .split_once('#')
+        .ok_or(Error::Decryption("The url does not contain a key".into()))?;
+
+    let response = CLIENT
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| Error::from(err))?;
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(Error::NonOkStatusCode(status.to_string(), text));
+    }
+    let data = response.bytes().await.map_err(|err| Error::from(err))?;
+
+    let (mime_type, bytes) = decrypt(&data, key)?;
+    let output = output.unwrap_or_else(|| {
+        let extension = mime_guess::get_mime_extensions_str(&mime_type)
+            .and_then(|extensions| extensions.first())
+            .unwrap_or(&"bin");
+        PathBuf::from(format!("shareshot-decrypted.{extension}"))
+    });
+    std::fs::write(&output, bytes).map_err(|err| Error::from(err))?;
+
+    Ok(output)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use aes_gcm::{aead::OsRng, Aes256Gcm, KeyInit};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    use super::{decrypt, encrypt};
+
+    #[test]
+    pub fn test_round_trip() {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let data = encrypt("image/png", b"image data", &key).unwrap();
+
+        let (mime_type, bytes) = decrypt(&data, &URL_SAFE_NO_PAD.encode(key)).unwrap();
+        assert_eq!(mime_type, "image/png");
+        assert_eq!(bytes, b"image data");
+    }
+
+    #[test]
+    pub fn test_wrong_key() {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let data = encrypt("image/png", b"image data", &key).unwrap();
+
+        let other_key = Aes256Gcm::generate_key(OsRng);
+        assert!(decrypt(&data, &URL_SAFE_NO_PAD.encode(other_key)).is_err());
+    }
+}
@@ -8,7 +8,7 @@ pub enum Error {
     /// Unable to create D-Bus service with provided parameters.
     /// Might be caused due to shareshot already being started.
     #[error("Failed to create dbus service: {0}")]
-    DbusCreate(Box<dyn std::error::Error>),
+    DbusCreate(Box<dyn std::error::Error + Send + Sync>),
     /// D-Bus error
     ///
     /// Unable to send message to D-Bus daemon.
@@ -81,10 +81,133 @@ pub enum Error {
     /// Did the currently used portal implement the screenshot protocol properly?
     #[error("Image not found, was the screenshot taken?")]
     ImageNotFound,
+    /// File not found error
+    ///
+    /// The file which should be uploaded does not exist.
+    /// Make sure the path is correct and readable by ShareShot.
+    #[error("File '{0}' not found")]
+    FileNotFound(String),
+    /// Payload rejected error
+    ///
+    /// The file does not match the allowed types or the maximum size of any upload profile.
+    /// Check the `allowed_types` and `max_size` properties of your upload profiles.
+    #[error("The file was rejected by all upload profiles: {0}")]
+    PayloadRejected(String),
     /// Clipboard error,
     ///
     /// Failed to copy content to clipboard.
     /// The system is most likely incompatible with broadly used clipboard protocols.
     #[error("Failed to copy text to clipboard: {0}")]
     Clipboard(#[from] arboard::Error),
+    /// Unknown profile error
+    ///
+    /// A profile referenced in the configuration does not exist.
+    /// Double check the `fallback` properties of your upload profiles.
+    #[error("Upload profile '{0}' does not exist")]
+    UnknownProfile(String),
+    /// History load error
+    ///
+    /// The upload history could not be parsed.
+    /// The history file was most likely edited by hand or written by an incompatible version.
+    #[error("Failed to load upload history")]
+    HistoryLoad,
+    /// History save error
+    ///
+    /// The upload could not be added to the upload history.
+    /// The data folder was most likely not created properly or the user doesn't have permission to edit it.
+    #[error("Failed to save upload history")]
+    HistorySave,
+    /// Encryption error
+    ///
+    /// The image could not be encrypted before uploading.
+    #[error("Failed to encrypt image")]
+    Encryption,
+    /// Decryption error
+    ///
+    /// The downloaded file could not be decrypted.
+    /// Make sure the url including its `#` fragment was copied completely.
+    #[error("Failed to decrypt image: {0}")]
+    Decryption(String),
+    /// Unsupported operation error
+    ///
+    /// The upload backend of the profile does not support the requested operation.
+    #[error("The upload backend does not support {0}")]
+    Unsupported(&'static str),
+    /// Curl import error
+    ///
+    /// The curl command could not be converted into an upload configuration.
+    /// Make sure the complete command including the url was provided.
+    #[error("Failed to import curl command: {0}")]
+    CurlImport(String),
+    /// Image processing error
+    ///
+    /// The image could not be decoded or encoded.
+    /// The file is most likely not an image or uses an unsupported format.
+    #[error("Failed to process image: {0}")]
+    ImageProcessing(#[from] image::ImageError),
+    /// Metadata error
+    ///
+    /// The metadata of the image could not be edited.
+    /// The image is most likely corrupted.
+    #[error("Failed to edit image metadata: {0}")]
+    Metadata(#[from] img_parts::Error),
+    /// Optimization error
+    ///
+    /// The PNG image could not be optimized.
+    #[error("Failed to optimize image: {0}")]
+    Optimization(#[from] oxipng::PngError),
+    /// Payload too large error
+    ///
+    /// The file could not be shrunk below the configured maximum size.
+    /// Increase the `max_size` of the profile or lower the quality.
+    #[error("The file is {0} bytes large, exceeding the limit of {1} bytes")]
+    PayloadTooLarge(u64, u64),
+    /// Undecodable image error
+    ///
+    /// The image exceeds the maximum size but can't be decoded to shrink it, e.g. AVIF images.
+    /// Convert the image into another format or increase the `max_size` of the profile.
+    #[error("{0} images can't be shrunk to the maximum size")]
+    UndecodableImage(String),
+    /// Verification error
+    ///
+    /// The uploaded url could not be downloaded or served an unexpected content type.
+    /// The upload is not retried on fallback profiles, the file may have to be deleted manually.
+    /// Make sure the response parse pattern produces the correct url.
+    #[error("The uploaded url {0} could not be verified: {1}")]
+    VerificationFailed(String, String),
+    /// GraphQL error
+    ///
+    /// The GraphQL server responded with errors.
+    /// Make sure the mutation and variables match the schema of the server.
+    #[error("GraphQL request failed: {0}")]
+    GraphQl(String),
+    /// Font not found error
+    ///
+    /// The configured font or the default DejaVu Sans font does not exist.
+    /// Install DejaVu Sans or configure the `font` of the watermark.
+    #[error("Font {0} not found")]
+    FontNotFound(String),
+    /// Invalid font error
+    ///
+    /// The configured watermark font could not be loaded.
+    /// Make sure it is a TrueType or OpenType font.
+    #[error("Failed to load watermark font: {0}")]
+    InvalidFont(#[from] ab_glyph::InvalidFont),
+    /// OCR error
+    ///
+    /// Tesseract could not recognize the text of the image.
+    /// Make sure tesseract and the configured language data are installed.
+    #[error("Failed to recognize text: {0}")]
+    Ocr(String),
+    /// Invalid pattern error
+    ///
+    /// A configured redaction pattern is not a valid regular expression.
+    #[error("Invalid redaction pattern '{0}': {1}")]
+    InvalidPattern(String, regex::Error),
+    /// Code scan error
+    ///
+    /// zbarimg could not decode a QR code or barcode of the image.
+    /// Make sure zbar is installed and the code is fully visible in the capture.
+    #[error("Failed to scan code: {0}")]
+    CodeScan(String),
 }
@@ -0,0 +1,102 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// A single upload stored in the upload history.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HistoryEntry {
+    // Seconds since the unix epoch
+    pub timestamp: u64,
+    pub profile: String,
+    pub url: String,
+    #[serde(default)]
+    pub short_url: Option<String>,
+    // The hex encoded SHA-256 hash of the uploaded image
+    #[serde(default)]
+    pub hash: Option<String>,
+    // Bytes saved by optimizing the image before uploading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_bytes: Option<u64>,
+    // Number of automatically redacted matches per pattern, never the matched text itself
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub redactions: BTreeMap<String, usize>,
+}
+
+impl HistoryEntry {
+    /// Creates a new history entry with the current time as timestamp.
+    pub fn new(profile: String, url: String) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            profile,
+            url,
+            ..Default::default()
+        }
+    }
+}
+
+/// Appends an entry to the upload history on the file system.
+pub fn append_history(entry: &HistoryEntry) -> Result<(), Error> {
+    let path = history_path().ok_or(Error::HistorySave)?;
+    let line = serde_json::to_string(entry).map_err(|_| Error::HistorySave)?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| Error::from(err))?;
+    writeln!(file, "{line}").map_err(|err| Error::from(err))
+}
+
+/// Loads the upload history from the file system, oldest entries first.
+pub fn load_history() -> Result<Vec<HistoryEntry>, Error> {
+    match history_path() {
+        Some(path) if path.exists() => {
+            let file = File::open(path).map_err(|err| Error::from(err))?;
+            let mut entries = Vec::new();
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|err| Error::from(err))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                entries.push(serde_json::from_str(&line).map_err(|_| Error::HistoryLoad)?);
+            }
+            Ok(entries)
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Finds the most recent upload of an image with the given hash to the given profile.
+///
+/// # Returns
+/// The history entry of the upload or `None` if the image wasn't uploaded yet
+pub fn find_upload(profile: &str, hash: &str) -> Result<Option<HistoryEntry>, Error> {
+    Ok(load_history()?.into_iter().rev().find(|entry| {
+        entry.profile == profile && entry.hash.as_deref() == Some(hash)
+    }))
+}
+
+/// Creates and returns the history path, tests never touch the history of the user.
+fn history_path() -> Option<PathBuf> {
+    #[cfg(not(test))]
+    let mut home_dir = home::home_dir()?;
+    #[cfg(test)]
+    let mut home_dir = std::env::temp_dir();
+    home_dir.push(".local");
+    home_dir.push("share");
+    home_dir.push("shareshot");
+    fs::create_dir_all(&home_dir).ok()?;
+    home_dir.push("history.jsonl");
+    Some(home_dir)
+}
@@ -1,25 +1,111 @@
-use clap::Parser;
+use std::{io::Read, path::PathBuf};
+
+use capture::CaptureMode;
+use clap::{Parser, Subcommand};
+use config::PRIMARY_PROFILE;
+use error::Error;
+use payload::Payload;
+use processing::Region;
 use log::error;
 
+pub mod annotation;
 pub mod application;
+pub mod barcode;
 pub mod capture;
 pub mod config;
+pub mod curl;
 pub mod dbus;
+pub mod encryption;
 pub mod error;
-pub mod image;
+pub mod history;
+pub mod ocr;
 pub mod parser;
+pub mod payload;
+pub mod processing;
+pub mod template;
 pub mod upload;
 
 #[derive(Parser, Debug)]
 struct ShareShotArgs {
     #[arg(long, default_value_t = false)]
     capture: bool,
+    /// What to do with the capture
+    #[arg(long, value_enum, default_value_t = CaptureMode::Upload, requires = "capture")]
+    mode: CaptureMode,
+    /// Uploads the capture even if the same image was uploaded before
+    #[arg(long, default_value_t = false, requires = "capture")]
+    force: bool,
+    /// Redacts a region of the capture, given as x,y,width,height (can be repeated)
+    #[arg(long, value_name = "REGION", requires = "capture")]
+    redact: Vec<Region>,
+    #[command(subcommand)]
+    command: Option<ShareShotCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+enum ShareShotCommand {
+    /// Downloads and decrypts an encrypted upload
+    Decrypt {
+        /// The url of the upload including the key fragment
+        url: String,
+        /// The path to write the decrypted file to
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Prints the upload requests of a profile without sending them
+    Preview {
+        /// The profile to preview
+        #[arg(short, long, default_value = PRIMARY_PROFILE)]
+        profile: String,
+        /// The file to upload, an empty placeholder image is used if not set
+        #[arg(short, long)]
+        file: Option<String>,
+        /// Prints the requests as curl commands
+        #[arg(long, default_value_t = false)]
+        curl: bool,
+    },
+    /// Uploads a file using the running ShareShot instance
+    Upload {
+        /// The file to upload
+        file: PathBuf,
+        /// Uploads the file even if it was uploaded before
+        #[arg(long, default_value_t = false)]
+        force: bool,
+    },
+    /// Pastes text to the paste profile using the running ShareShot instance
+    Paste {
+        /// The text to paste, read from stdin if not set
+        text: Option<String>,
+        /// Pastes the text even if it was pasted before
+        #[arg(long, default_value_t = false)]
+        force: bool,
+    },
+    /// Imports the upload server configuration from a curl command
+    ImportCurl {
+        /// The curl command, e.g. from the documentation of the upload server
+        command: String,
+        /// The profile to import the configuration into
+        #[arg(short, long, default_value = PRIMARY_PROFILE)]
+        profile: String,
+    },
 }
 
 impl ShareShotArgs {
     fn capture(&self) -> bool {
         self.capture
     }
+
+    fn mode(&self) -> CaptureMode {
+        self.mode
+    }
+
+    fn force(&self) -> bool {
+        self.force
+    }
+
+    fn redact(&self) -> &[Region] {
+        &self.redact
+    }
 }
 
 #[tokio::main]
@@ -27,8 +113,47 @@ async fn main() {
     pretty_env_logger::init();
     let args = ShareShotArgs::parse();
 
+    match args.command {
+        Some(ShareShotCommand::Decrypt { url, output }) => {
+            match encryption::download_and_decrypt(&url, output).await {
+                Ok(path) => println!("Decrypted file saved to {}", path.display()),
+                Err(err) => error!("Failed to decrypt upload: {err}"),
+            }
+            return;
+        }
+        Some(ShareShotCommand::Preview {
+            profile,
+            file,
+            curl,
+        }) => {
+            if let Err(err) = print_preview(&profile, file, curl).await {
+                error!("Failed to preview upload: {err}");
+            }
+            return;
+        }
+        Some(ShareShotCommand::Upload { file, force }) => {
+            if let Err(err) = request_upload(file, force).await {
+                error!("Failed to upload file: {err}");
+            }
+            return;
+        }
+        Some(ShareShotCommand::Paste { text, force }) => {
+            if let Err(err) = request_paste(text, force).await {
+                error!("Failed to paste text: {err}");
+            }
+            return;
+        }
+        Some(ShareShotCommand::ImportCurl { command, profile }) => {
+            if let Err(err) = import_curl(&command, &profile).await {
+                error!("Failed to import curl command: {err}");
+            }
+            return;
+        }
+        None => {}
+    }
+
     match if args.capture() {
-        dbus::client::request_capture().await
+        dbus::client::request_capture(args.mode(), args.force(), args.redact()).await
     } else {
         application::create_application().await
     } {
@@ -38,3 +163,67 @@ async fn main() {
         }
     }
 }
+
+/// Requests the upload of a file, the daemon doesn't share the working directory of the CLI.
+async fn request_upload(file: PathBuf, force: bool) -> Result<(), Error> {
+    let path = file
+        .canonicalize()
+        .map_err(|_| Error::FileNotFound(file.display().to_string()))?;
+    dbus::client::request_upload(&path.to_string_lossy(), force).await
+}
+
+/// Requests a text paste, reading the text from stdin if none was given.
+async fn request_paste(text: Option<String>, force: bool) -> Result<(), Error> {
+    let text = match text {
+        Some(text) => text,
+        None => {
+            let mut text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|err| Error::from(err))?;
+            text
+        }
+    };
+    dbus::client::request_paste(&text, force).await
+}
+
+/// Prints the requests an upload to the given profile would make.
+async fn print_preview(profile: &str, file: Option<String>, curl: bool) -> Result<(), Error> {
+    let payload = match file {
+        Some(path) => Payload::read(path)?,
+        None => Payload::placeholder(),
+    };
+
+    for request in upload::preview_upload(profile, &payload).await? {
+        match curl {
+            true => println!("{}\n", request.to_curl()),
+            false => println!("{request}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Imports a curl command into the given profile and saves the configuration.
+async fn import_curl(command: &str, profile: &str) -> Result<(), Error> {
+    let import = curl::parse_curl_command(command)?;
+    for warning in &import.warnings {
+        println!("Warning: {warning}");
+    }
+
+    let mut config = application::CONFIG.lock().await;
+    match profile {
+        PRIMARY_PROFILE => config.upload_server.http = import.config,
+        _ => {
+            config
+                .profiles
+                .entry(profile.to_string())
+                .or_default()
+                .http = import.config
+        }
+    }
+    config.save()?;
+
+    println!("Imported curl command into profile '{profile}'");
+    Ok(())
+}
@@ -0,0 +1,238 @@
+use std::{collections::BTreeMap, process::Stdio};
+
+use regex::Regex;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::{error::Error, payload::Payload, processing::Region};
+
+/// A word recognized by tesseract.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecognizedWord {
+    pub text: String,
+    pub region: Region,
+    // Page, block, paragraph and line number, identifying the line of the word
+    line: (u32, u32, u32, u32),
+}
+
+/// Recognizes all words of an image using tesseract.
+///
+/// # Returns
+/// The words in reading order, including their position in the image
+pub async fn recognize_words(
+    payload: &Payload,
+    language: &str,
+) -> Result<Vec<RecognizedWord>, Error> {
+    let tsv = run_tesseract(payload, language, Some("tsv")).await?;
+    Ok(parse_tsv(&tsv))
+}
+
+/// Recognizes the text of an image using tesseract.
+///
+/// # Returns
+/// The recognized text, an error if the image contains no text
+pub async fn recognize_text(payload: &Payload, language: &str) -> Result<String, Error> {
+    let text = run_tesseract(payload, language, None).await?;
+    match text.trim() {
+        "" => Err(Error::Ocr("no text was recognized".into())),
+        text => Ok(text.to_string()),
+    }
+}
+
+/// Finds the regions of all text matching one of the named patterns.
+///
+/// Patterns are matched against whole lines, so matches may span multiple words, e.g. an IBAN
+/// split into groups.
+///
+/// # Returns
+/// The name of the matching pattern and the region of the matched words, grown by the padding
+pub fn find_sensitive_text(
+    words: &[RecognizedWord],
+    patterns: &BTreeMap<String, String>,
+    padding: u32,
+) -> Result<Vec<(String, Region)>, Error> {
+    let patterns = patterns
+        .iter()
+        .map(|(name, pattern)| {
+            Regex::new(pattern)
+                .map(|regex| (name, regex))
+                .map_err(|err| Error::InvalidPattern(name.clone(), err))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut found = Vec::new();
+    for line in words.chunk_by(|first, second| first.line == second.line) {
+        // The byte range of every word in the joined line
+        let mut text = String::new();
+        let mut spans = Vec::new();
+        for word in line {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            spans.push((text.len(), text.len() + word.text.len()));
+            text.push_str(&word.text);
+        }
+
+        for (name, regex) in &patterns {
+            for found_match in regex.find_iter(&text) {
+                let region = line
+                    .iter()
+                    .zip(&spans)
+                    .filter(|(_, (start, end))| {
+                        *start < found_match.end() && *end > found_match.start()
+                    })
+                    .map(|(word, _)| word.region)
+                    .reduce(union)
+                    .map(|region| pad(region, padding));
+                if let Some(region) = region {
+                    found.push((name.to_string(), region));
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Runs tesseract on the image data of a payload.
+///
+/// # Returns
+/// The output of tesseract, plain text unless another output format is given
+async fn run_tesseract(
+    payload: &Payload,
+    language: &str,
+    format: Option<&str>,
+) -> Result<String, Error> {
+    let mut child = Command::new("tesseract")
+        .args(["stdin", "stdout", "-l", language])
+        .args(format)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| Error::Ocr(format!("failed to start tesseract: {err}")))?;
+
+    let mut stdin = child.stdin.take().expect("The stdin of tesseract is piped");
+    stdin
+        .write_all(payload.bytes())
+        .await
+        .map_err(|err| Error::from(err))?;
+    drop(stdin);
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|err| Error::from(err))?;
+    if !output.status.success() {
+        return Err(Error::Ocr(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parses the words of the TSV output of tesseract.
+///
+/// Rows of pages, blocks, paragraphs and lines as well as empty words are skipped.
+fn parse_tsv(tsv: &str) -> Vec<RecognizedWord> {
+    tsv.lines()
+        .skip(1)
+        .filter_map(|row| {
+            let columns = row.split('\t').collect::<Vec<_>>();
+            let [level, page, block, paragraph, line, _word, left, top, width, height, _confidence, text] =
+                columns[..]
+            else {
+                return None;
+            };
+            let number = |value: &str| value.parse::<u32>().ok();
+            if level != "5" || text.trim().is_empty() {
+                return None;
+            }
+
+            Some(RecognizedWord {
+                text: text.trim().to_string(),
+                region: Region {
+                    x: number(left)?,
+                    y: number(top)?,
+                    width: number(width)?,
+                    height: number(height)?,
+                },
+                line: (number(page)?, number(block)?, number(paragraph)?, number(line)?),
+            })
+        })
+        .collect()
+}
+
+/// The smallest region containing both regions.
+fn union(first: Region, second: Region) -> Region {
+    let (left, top) = (first.x.min(second.x), first.y.min(second.y));
+    let right = (first.x + first.width).max(second.x + second.width);
+    let bottom = (first.y + first.height).max(second.y + second.height);
+    Region {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    }
+}
+
+fn pad(region: Region, padding: u32) -> Region {
+    Region {
+        x: region.x.saturating_sub(padding),
+        y: region.y.saturating_sub(padding),
+        width: region.width + padding * 2,
+        height: region.height + padding * 2,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{config::AutoRedactConfig, processing::Region};
+
+    use super::{find_sensitive_text, parse_tsv};
+
+    const TSV: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext
+1\t1\t0\t0\t0\t0\t0\t0\t800\t200\t-1\t
+4\t1\t1\t1\t1\t0\t10\t10\t400\t20\t-1\t
+5\t1\t1\t1\t1\t1\t10\t10\t60\t20\t96.5\tContact
+5\t1\t1\t1\t1\t2\t80\t10\t200\t20\t95.1\tjane.doe@example.com
+5\t1\t1\t1\t2\t1\t10\t40\t40\t20\t91.0\tIBAN
+5\t1\t1\t1\t2\t2\t60\t40\t60\t20\t90.2\tDE89
+5\t1\t1\t1\t2\t3\t130\t40\t60\t20\t90.4\t3704
+5\t1\t1\t1\t2\t4\t200\t40\t60\t20\t92.3\t0044
+5\t1\t1\t1\t2\t5\t270\t40\t60\t20\t93.0\t0532
+5\t1\t1\t1\t2\t6\t340\t40\t60\t20\t94.8\t0130
+5\t1\t1\t1\t2\t7\t410\t40\t30\t20\t95.5\t00
+5\t1\t1\t1\t3\t1\t10\t70\t30\t20\t95.0\t \n";
+
+    #[test]
+    pub fn test_find_sensitive_text() {
+        let words = parse_tsv(TSV);
+        assert_eq!(words.len(), 9);
+
+        let found = find_sensitive_text(&words, &AutoRedactConfig::default().patterns, 2).unwrap();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "email".to_string(),
+                    Region {
+                        x: 78,
+                        y: 8,
+                        width: 204,
+                        height: 24,
+                    }
+                ),
+                // The IBAN spans multiple words, the label is kept
+                (
+                    "iban".to_string(),
+                    Region {
+                        x: 58,
+                        y: 38,
+                        width: 384,
+                        height: 24,
+                    }
+                ),
+            ]
+        );
+    }
+}
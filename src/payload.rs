@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::Error;
+
+// A transparent 1x1 PNG, which passes through all image processing steps
+const PLACEHOLDER_PNG: [u8; 68] = [
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+    0x89, 0x00, 0x00, 0x00, 0x0b, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x60, 0x00, 0x02, 0x00,
+    0x00, 0x05, 0x00, 0x01, 0xe9, 0xfa, 0xdc, 0xd8, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+    0xae, 0x42, 0x60, 0x82,
+];
+
+/// Stores data about a read file which can be uploaded, e.g. a screenshot.
+#[derive(Clone)]
+pub struct Payload {
+    path: PathBuf,
+    bytes: Vec<u8>,
+    mime_type: String,
+    // Set for text pastes, which are only accepted by text profiles
+    text: bool,
+}
+
+impl Payload {
+    /// Creates a blank image, used to preview requests without a screenshot.
+    pub fn placeholder() -> Self {
+        Self::new(
+            PathBuf::from("screenshot.png"),
+            PLACEHOLDER_PNG.to_vec(),
+            "image/png".into(),
+        )
+    }
+
+    /// Creates a payload from data which is already in memory.
+    pub fn new(path: PathBuf, bytes: Vec<u8>, mime_type: String) -> Self {
+        Self {
+            path,
+            bytes,
+            mime_type,
+            text: false,
+        }
+    }
+
+    /// Creates a text paste, e.g. from the clipboard.
+    pub fn text(text: String) -> Self {
+        Self {
+            path: PathBuf::from("paste.txt"),
+            bytes: text.into_bytes(),
+            mime_type: "text/plain".into(),
+            text: true,
+        }
+    }
+
+    /// Reads a file from a given path.
+    ///
+    /// # Returns
+    /// The file data
+    pub fn read<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path> + ToString + Display,
+    {
+        let path_ref = path.as_ref();
+
+        if !path_ref.exists() {
+            return Err(Error::FileNotFound(path.to_string()));
+        }
+
+        let mime_type = mime_guess::from_path(&path);
+        let mut file = File::open(path_ref).map_err(|err| Error::from(err))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)
+            .map_err(|err| Error::from(err))?;
+
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            bytes,
+            mime_type: mime_type.first_or_octet_stream().to_string(),
+            text: false,
+        })
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn bytes(&self) -> &Vec<u8> {
+        &self.bytes
+    }
+
+    pub fn mime_type(&self) -> &String {
+        &self.mime_type
+    }
+
+    /// Whether the payload is a text paste.
+    pub fn is_text(&self) -> bool {
+        self.text
+    }
+
+    /// The size of the payload in bytes.
+    pub fn size(&self) -> u64 {
+        self.bytes.len() as u64
+    }
+
+    /// The hex encoded SHA-256 hash of the payload bytes.
+    pub fn hash(&self) -> String {
+        format!("{:x}", Sha256::digest(&self.bytes))
+    }
+
+    /// The name of the file, without its directory.
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// The variables describing the payload which can be referenced in request templates.
+    pub fn template_variables(&self) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("file_name".to_string(), self.file_name()),
+            ("mime_type".to_string(), self.mime_type.clone()),
+            ("size".to_string(), self.size().to_string()),
+        ])
+    }
+}
+
+/// Checks whether a MIME type matches a pattern, which is either a MIME type, a wildcard
+/// subtype like `image/*` or `*` for all types.
+pub fn mime_matches(pattern: &str, mime_type: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => mime_type
+            .split_once('/')
+            .is_some_and(|(kind, _)| kind == prefix),
+        None => pattern == "*" || pattern == mime_type,
+    }
+}
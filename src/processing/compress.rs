@@ -0,0 +1,141 @@
+use image::{imageops::FilterType, DynamicImage, ImageFormat};
+
+use crate::{error::Error, payload::Payload};
+
+use super::{decode_image, encode_image_with_quality};
+
+// Images are not downscaled below this width and height
+const MIN_DIMENSION: u32 = 16;
+// Lossy formats are recompressed in steps of this size down to the minimum quality
+const QUALITY_STEP: usize = 10;
+const MIN_QUALITY: u8 = 40;
+
+/// Shrinks an image until its data is at most `max_size` bytes large.
+///
+/// The image keeps its format. Lossy formats are recompressed with a decreasing quality first,
+/// starting at the given quality, then the image is downscaled step by step until it fits.
+/// Payloads which already fit are returned unchanged.
+///
+/// # Returns
+/// The payload fitting into the size limit
+pub fn fit_to_size(payload: &Payload, max_size: u64, quality: u8) -> Result<Payload, Error> {
+    fit_to_size_from(payload, None, max_size, quality)
+}
+
+/// Shrinks an image like `fit_to_size`, encoding the given source image instead of decoding
+/// the payload.
+///
+/// The source allows shrinking payloads in formats which can only be encoded, like AVIF.
+///
+/// # Returns
+/// The payload fitting into the size limit
+pub fn fit_to_size_from(
+    payload: &Payload,
+    source: Option<&DynamicImage>,
+    max_size: u64,
+    quality: u8,
+) -> Result<Payload, Error> {
+    if payload.size() <= max_size {
+        return Ok(payload.clone());
+    }
+    let format = match ImageFormat::from_mime_type(payload.mime_type()) {
+        Some(format) if payload.mime_type().starts_with("image/") => format,
+        _ => return Err(Error::PayloadTooLarge(payload.size(), max_size)),
+    };
+
+    let qualities = match format {
+        ImageFormat::Jpeg | ImageFormat::Avif => (MIN_QUALITY.min(quality)..=quality)
+            .rev()
+            .step_by(QUALITY_STEP)
+            .collect(),
+        _ => vec![quality],
+    };
+    let lowest_quality = qualities.last().copied().unwrap_or(quality);
+    let fitted =
+        |bytes: Vec<u8>| Payload::new(payload.path().clone(), bytes, payload.mime_type().clone());
+
+    let mut image = match source {
+        Some(source) => source.clone(),
+        None => decode_image(payload)
+            .map_err(|_| Error::UndecodableImage(payload.mime_type().clone()))?,
+    };
+    let mut size = payload.size();
+    for quality in qualities {
+        let bytes = encode_image_with_quality(&image, format, quality)?;
+        size = bytes.len() as u64;
+        if size <= max_size {
+            return Ok(fitted(bytes));
+        }
+    }
+
+    while image.width() > MIN_DIMENSION || image.height() > MIN_DIMENSION {
+        image = image.resize(
+            (image.width() * 3 / 4).max(1),
+            (image.height() * 3 / 4).max(1),
+            FilterType::Triangle,
+        );
+        let bytes = encode_image_with_quality(&image, format, lowest_quality)?;
+        size = bytes.len() as u64;
+        if size <= max_size {
+            return Ok(fitted(bytes));
+        }
+    }
+    Err(Error::PayloadTooLarge(size, max_size))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::PathBuf;
+
+    use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+
+    use crate::{
+        payload::Payload,
+        processing::{encode_jpeg, tests::png_payload},
+    };
+
+    use super::fit_to_size;
+
+    // Noise compresses badly, forcing the image to be recompressed or downscaled
+    fn noise() -> RgbImage {
+        RgbImage::from_fn(256, 256, |x, y| {
+            Rgb([(x * 31 + y * 17) as u8, (x * y) as u8, (x ^ y) as u8])
+        })
+    }
+
+    #[test]
+    pub fn test_fit_to_size() {
+        let payload = png_payload(noise());
+
+        let fitted = fit_to_size(&payload, 40000, 80).unwrap();
+        assert!(fitted.size() <= 40000);
+        assert_eq!(fitted.mime_type(), "image/png");
+        assert_eq!(fitted.file_name(), "screenshot.png");
+        assert!(image::load_from_memory(fitted.bytes()).unwrap().width() < 256);
+
+        let unchanged = fit_to_size(&payload, payload.size(), 80).unwrap();
+        assert_eq!(unchanged.bytes(), payload.bytes());
+    }
+
+    #[test]
+    pub fn test_fit_to_size_lowers_quality_first() {
+        let image = DynamicImage::ImageRgb8(noise());
+        let payload = Payload::new(
+            PathBuf::from("screenshot.jpg"),
+            encode_jpeg(&image, 95).unwrap(),
+            "image/jpeg".into(),
+        );
+
+        // Fits at a lower quality without downscaling
+        let max_size = encode_jpeg(&image, 60).unwrap().len() as u64;
+        let fitted = fit_to_size(&payload, max_size, 90).unwrap();
+        assert!(fitted.size() <= max_size);
+        assert_eq!(fitted.mime_type(), "image/jpeg");
+        assert_eq!(
+            image::load_from_memory(fitted.bytes())
+                .unwrap()
+                .dimensions(),
+            (256, 256)
+        );
+    }
+}
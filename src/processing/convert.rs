@@ -0,0 +1,113 @@
+use image::ImageFormat;
+
+use crate::{
+    config::{ConvertConfig, OutputFormat},
+    error::Error,
+    payload::Payload,
+};
+
+use super::{decode_image, encode_image_with_quality, DEFAULT_QUALITY};
+
+/// Converts an image into the configured format.
+///
+/// Payloads which are no images, can't be decoded (e.g. SVG or HEIC) or already use the target
+/// format are returned unchanged.
+///
+/// # Returns
+/// The converted payload, with MIME type and file extension matching the new format
+pub fn convert_image(payload: &Payload, config: &ConvertConfig) -> Result<Payload, Error> {
+    let format = config.format;
+    if !payload.mime_type().starts_with("image/") || payload.mime_type() == format.mime_type() {
+        return Ok(payload.clone());
+    }
+
+    // Conversion is optional, the original is still accepted by the server
+    let image = match decode_image(payload) {
+        Ok(image) => image,
+        Err(err) => {
+            log::warn!(
+                "Can't convert {} ({}), uploading it unchanged: {err}",
+                payload.file_name(),
+                payload.mime_type()
+            );
+            return Ok(payload.clone());
+        }
+    };
+    let image_format = match format {
+        OutputFormat::Png => ImageFormat::Png,
+        OutputFormat::Jpeg => ImageFormat::Jpeg,
+        OutputFormat::WebP => ImageFormat::WebP,
+        OutputFormat::Avif => ImageFormat::Avif,
+    };
+    let quality = config.quality.unwrap_or(DEFAULT_QUALITY);
+
+    Ok(Payload::new(
+        payload.path().with_extension(format.extension()),
+        encode_image_with_quality(&image, image_format, quality)?,
+        format.mime_type().into(),
+    ))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::PathBuf;
+
+    use image::{ImageFormat, Rgba, RgbaImage};
+
+    use crate::{
+        config::{ConvertConfig, OutputFormat},
+        payload::Payload,
+        processing::tests::png_payload,
+    };
+
+    use super::convert_image;
+
+    #[test]
+    pub fn test_convert_image() {
+        let image = RgbaImage::from_pixel(8, 8, Rgba([255, 0, 0, 255]));
+        let payload = png_payload(image);
+
+        for (format, expected) in [
+            (OutputFormat::Jpeg, ImageFormat::Jpeg),
+            (OutputFormat::WebP, ImageFormat::WebP),
+            (OutputFormat::Avif, ImageFormat::Avif),
+        ] {
+            let converted = convert_image(
+                &payload,
+                &ConvertConfig {
+                    format,
+                    quality: None,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(image::guess_format(converted.bytes()).unwrap(), expected);
+            assert_eq!(converted.mime_type(), format.mime_type());
+            assert_eq!(
+                converted.file_name(),
+                format!("screenshot.{}", format.extension())
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_convert_undecodable_image() {
+        let payload = Payload::new(
+            PathBuf::from("drawing.svg"),
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="8"/>"#.to_vec(),
+            "image/svg+xml".into(),
+        );
+
+        let converted = convert_image(
+            &payload,
+            &ConvertConfig {
+                format: OutputFormat::WebP,
+                quality: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(converted.bytes(), payload.bytes());
+        assert_eq!(converted.mime_type(), "image/svg+xml");
+        assert_eq!(converted.file_name(), "drawing.svg");
+    }
+}
@@ -0,0 +1,141 @@
+use bytes::Bytes;
+use img_parts::{jpeg::Jpeg, png::Png, webp::WebP, ImageEXIF, ImageICC};
+
+use crate::{config::MetadataPolicy, error::Error, payload::Payload};
+
+// PNG chunks describing the color space of the image
+const PNG_COLOR_CHUNKS: [&[u8; 4]; 5] = [b"iCCP", b"sRGB", b"gAMA", b"cHRM", b"cICP"];
+// Ancillary PNG chunks which are required to display the image correctly
+const PNG_REQUIRED_CHUNKS: [&[u8; 4]; 4] = [b"tRNS", b"acTL", b"fcTL", b"fdAT"];
+
+const JPEG_APP0: u8 = 0xE0;
+const JPEG_APP2: u8 = 0xE2;
+// Adobe segment, required to decode CMYK images correctly
+const JPEG_APP14: u8 = 0xEE;
+const JPEG_APP15: u8 = 0xEF;
+const JPEG_COM: u8 = 0xFE;
+
+/// Removes metadata like EXIF data (including GPS locations) and text chunks from JPEG, PNG
+/// and WebP images according to the given policy.
+///
+/// Other payloads are returned unchanged.
+///
+/// # Returns
+/// The payload without the removed metadata
+pub fn apply_metadata_policy(payload: &Payload, policy: MetadataPolicy) -> Result<Payload, Error> {
+    let keep_color_profile = match policy {
+        MetadataPolicy::Keep => return Ok(payload.clone()),
+        MetadataPolicy::Strip => false,
+        MetadataPolicy::ColorProfile => true,
+    };
+    let data = Bytes::from(payload.bytes().clone());
+
+    let bytes = match payload.mime_type().as_str() {
+        "image/jpeg" => {
+            let mut jpeg = Jpeg::from_bytes(data).map_err(|err| Error::from(err))?;
+            jpeg.segments_mut()
+                .retain(|segment| match segment.marker() {
+                    JPEG_APP2 => {
+                        keep_color_profile && segment.contents().starts_with(b"ICC_PROFILE\0")
+                    }
+                    JPEG_APP0 | JPEG_APP14 => true,
+                    marker => !(JPEG_APP0..=JPEG_APP15).contains(&marker) && marker != JPEG_COM,
+                });
+            jpeg.encoder().bytes()
+        }
+        "image/png" => {
+            let mut png = Png::from_bytes(data).map_err(|err| Error::from(err))?;
+            png.chunks_mut().retain(|chunk| {
+                let kind = chunk.kind();
+                // Critical chunks start with an uppercase letter
+                kind[0].is_ascii_uppercase()
+                    || PNG_REQUIRED_CHUNKS.contains(&&kind)
+                    || (keep_color_profile && PNG_COLOR_CHUNKS.contains(&&kind))
+            });
+            png.encoder().bytes()
+        }
+        "image/webp" => {
+            let mut webp = WebP::from_bytes(data).map_err(|err| Error::from(err))?;
+            webp.set_exif(None);
+            webp.remove_chunks_by_id(img_parts::webp::CHUNK_XMP);
+            if !keep_color_profile {
+                webp.set_icc_profile(None);
+            }
+            webp.encoder().bytes()
+        }
+        _ => return Ok(payload.clone()),
+    };
+
+    Ok(Payload::new(
+        payload.path().clone(),
+        bytes.to_vec(),
+        payload.mime_type().clone(),
+    ))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::{io::Cursor, path::PathBuf};
+
+    use bytes::Bytes;
+    use exif::{In, Reader, Tag};
+    use image::{DynamicImage, RgbImage};
+    use img_parts::{jpeg::Jpeg, ImageEXIF, ImageICC};
+
+    use crate::{config::MetadataPolicy, payload::Payload, processing::encode_jpeg};
+
+    use super::apply_metadata_policy;
+
+    // Big endian TIFF structure with a GPS IFD containing `GPSLatitudeRef = N`
+    const GPS_EXIF: [u8; 44] = [
+        b'M', b'M', 0, 42, 0, 0, 0, 8, // Header, IFD0 at offset 8
+        0, 1, // IFD0 with one entry
+        0x88, 0x25, 0, 4, 0, 0, 0, 1, 0, 0, 0, 26, // GPS IFD pointer at offset 26
+        0, 0, 0, 0, // No next IFD
+        0, 1, // GPS IFD with one entry
+        0, 1, 0, 2, 0, 0, 0, 2, b'N', 0, 0, 0, // GPSLatitudeRef
+        0, 0, 0, 0, // No next IFD
+    ];
+
+    fn jpeg_with_metadata() -> Payload {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(16, 16));
+        let bytes = encode_jpeg(&image, 80).unwrap();
+        let mut jpeg = Jpeg::from_bytes(Bytes::from(bytes)).unwrap();
+        jpeg.set_exif(Some(Bytes::from_static(&GPS_EXIF)));
+        jpeg.set_icc_profile(Some(Bytes::from_static(b"fake icc profile")));
+
+        Payload::new(
+            PathBuf::from("photo.jpg"),
+            jpeg.encoder().bytes().to_vec(),
+            "image/jpeg".into(),
+        )
+    }
+
+    fn has_gps(payload: &Payload) -> bool {
+        Reader::new()
+            .read_from_container(&mut Cursor::new(payload.bytes()))
+            .is_ok_and(|exif| exif.get_field(Tag::GPSLatitudeRef, In::PRIMARY).is_some())
+    }
+
+    #[test]
+    pub fn test_strip_jpeg_gps() {
+        let payload = jpeg_with_metadata();
+        assert!(has_gps(&payload));
+
+        for policy in [MetadataPolicy::Strip, MetadataPolicy::ColorProfile] {
+            let stripped = apply_metadata_policy(&payload, policy).unwrap();
+            assert!(!has_gps(&stripped));
+
+            let jpeg = Jpeg::from_bytes(Bytes::from(stripped.bytes().clone())).unwrap();
+            assert!(jpeg.exif().is_none());
+            assert_eq!(
+                jpeg.icc_profile().is_some(),
+                policy == MetadataPolicy::ColorProfile
+            );
+            image::load_from_memory(stripped.bytes()).unwrap();
+        }
+
+        let kept = apply_metadata_policy(&payload, MetadataPolicy::Keep).unwrap();
+        assert!(has_gps(&kept));
+    }
+}
@@ -0,0 +1,293 @@
+use std::{borrow::Cow, io::Cursor, str::FromStr};
+
+use image::{
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, webp::WebPEncoder},
+    DynamicImage, ImageFormat,
+};
+
+use crate::{
+    config::{MetadataPolicy, UploadConfig},
+    error::Error,
+    payload::Payload,
+};
+
+use self::{
+    compress::fit_to_size_from, convert::convert_image, metadata::apply_metadata_policy,
+    optimize::optimize_png, resize::fit_dimensions, watermark::apply_watermark,
+};
+
+pub mod compress;
+pub mod convert;
+pub mod metadata;
+pub mod optimize;
+pub mod redact;
+pub mod resize;
+pub mod watermark;
+
+// Quality used when images have to be encoded as JPEG without a configured quality
+pub const DEFAULT_QUALITY: u8 = 80;
+// Encoding speed of AVIF (1-10), trades file size for encoding time
+const AVIF_SPEED: u8 = 8;
+
+/// A rectangular region of an image in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// Creates the region spanned by two opposite corners.
+    pub fn from_corners(start: (f32, f32), end: (f32, f32)) -> Self {
+        let (left, right) = (start.0.min(end.0).max(0.0), start.0.max(end.0).max(0.0));
+        let (top, bottom) = (start.1.min(end.1).max(0.0), start.1.max(end.1).max(0.0));
+        Self {
+            x: left.round() as u32,
+            y: top.round() as u32,
+            width: (right - left).round() as u32,
+            height: (bottom - top).round() as u32,
+        }
+    }
+
+    /// Clips the region to an image of the given size.
+    ///
+    /// # Returns
+    /// The clipped region or `None` if it lies outside of the image
+    pub fn clip(&self, width: u32, height: u32) -> Option<Region> {
+        let right = self.x.saturating_add(self.width).min(width);
+        let bottom = self.y.saturating_add(self.height).min(height);
+        if self.x >= right || self.y >= bottom {
+            return None;
+        }
+        Some(Region {
+            x: self.x,
+            y: self.y,
+            width: right - self.x,
+            height: bottom - self.y,
+        })
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    /// Parses a region in the format `x,y,width,height`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let values = value
+            .split(',')
+            .map(|value| value.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("invalid region '{value}': {err}"))?;
+        match values[..] {
+            [x, y, width, height] => Ok(Region {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(format!(
+                "invalid region '{value}', expected x,y,width,height"
+            )),
+        }
+    }
+}
+
+/// The result of processing a payload for a profile.
+pub struct Processed<'a> {
+    pub payload: Cow<'a, Payload>,
+    // Bytes saved by the lossless optimization
+    pub saved_bytes: Option<u64>,
+}
+
+/// Applies all processing steps configured by the profile to the payload.
+///
+/// Images are first downscaled to the maximum dimensions and watermarked, then converted,
+/// stripped of metadata, optimized and finally shrunk to the maximum file size.
+///
+/// # Returns
+/// The processed payload, borrowing the original if nothing was changed
+pub fn process_payload<'a>(
+    profile: &UploadConfig,
+    payload: &'a Payload,
+) -> Result<Processed<'a>, Error> {
+    let mut processed = Processed {
+        payload: Cow::Borrowed(payload),
+        saved_bytes: None,
+    };
+    // Lossy formats are always encoded with the configured quality
+    let quality = profile
+        .convert
+        .as_ref()
+        .and_then(|convert| convert.quality)
+        .unwrap_or(DEFAULT_QUALITY);
+
+    if profile.max_width.is_some() || profile.max_height.is_some() {
+        processed.payload = Cow::Owned(fit_dimensions(
+            &processed.payload,
+            profile.max_width.unwrap_or(u32::MAX),
+            profile.max_height.unwrap_or(u32::MAX),
+            quality,
+        )?);
+    }
+
+    if let Some(watermark) = &profile.watermark {
+        processed.payload = Cow::Owned(apply_watermark(&processed.payload, watermark, quality)?);
+    }
+
+    // Decoded before the conversion, as AVIF images can be encoded but not decoded
+    let mut source = None;
+    if let Some(convert) = &profile.convert {
+        if profile.max_size.is_some() {
+            source = decode_image(&processed.payload).ok();
+        }
+        processed.payload = Cow::Owned(convert_image(&processed.payload, convert)?);
+    }
+
+    if profile.metadata != MetadataPolicy::Keep {
+        processed.payload =
+            Cow::Owned(apply_metadata_policy(&processed.payload, profile.metadata)?);
+    }
+
+    if let Some(optimize) = &profile.optimize {
+        let optimized = optimize_png(&processed.payload, optimize)?;
+        let saved_bytes = processed.payload.size() - optimized.size();
+        if saved_bytes > 0 {
+            log::info!(
+                "Optimized {}, saved {saved_bytes} of {} bytes",
+                optimized.file_name(),
+                processed.payload.size()
+            );
+            processed.saved_bytes = Some(saved_bytes);
+            processed.payload = Cow::Owned(optimized);
+        }
+    }
+
+    // Other files exceeding the size limit are rejected by the profile instead
+    match profile.max_size {
+        Some(max_size) if processed.payload.mime_type().starts_with("image/") => {
+            processed.payload = Cow::Owned(fit_to_size_from(
+                &processed.payload,
+                source.as_ref(),
+                max_size,
+                quality,
+            )?);
+        }
+        _ => {}
+    }
+
+    Ok(processed)
+}
+
+/// Decodes the image data of a payload.
+///
+/// # Returns
+/// The decoded image
+pub fn decode_image(payload: &Payload) -> Result<DynamicImage, Error> {
+    image::load_from_memory(payload.bytes()).map_err(|err| Error::from(err))
+}
+
+/// Encodes an image in the given format, using the default quality for lossy formats.
+///
+/// # Returns
+/// The encoded image data
+pub fn encode_image(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, Error> {
+    encode_image_with_quality(image, format, DEFAULT_QUALITY)
+}
+
+/// Encodes an image in the given format, using the quality (1-100) for JPEG and AVIF.
+///
+/// # Returns
+/// The encoded image data
+pub fn encode_image_with_quality(
+    image: &DynamicImage,
+    format: ImageFormat,
+    quality: u8,
+) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    match format {
+        ImageFormat::Jpeg => return encode_jpeg(image, quality),
+        // Both encoders only support 8 bit RGB(A) images, WebP is always encoded lossless
+        ImageFormat::WebP => image
+            .to_rgba8()
+            .write_with_encoder(WebPEncoder::new_lossless(&mut bytes))
+            .map_err(|err| Error::from(err))?,
+        ImageFormat::Avif => image
+            .to_rgba8()
+            .write_with_encoder(AvifEncoder::new_with_speed_quality(
+                &mut bytes,
+                AVIF_SPEED,
+                quality.clamp(1, 100),
+            ))
+            .map_err(|err| Error::from(err))?,
+        format => image
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .map_err(|err| Error::from(err))?,
+    }
+    Ok(bytes)
+}
+
+/// Encodes an image as JPEG, dropping the alpha channel which JPEG doesn't support.
+///
+/// # Returns
+/// The encoded image data
+pub fn encode_jpeg(image: &DynamicImage, quality: u8) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, quality.clamp(1, 100))
+        .encode_image(&image.to_rgb8())
+        .map_err(|err| Error::from(err))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::PathBuf;
+
+    use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+
+    use crate::{
+        config::{ConvertConfig, OutputFormat, UploadConfig},
+        error::Error,
+        payload::Payload,
+    };
+
+    use super::{compress::fit_to_size, encode_image, encode_image_with_quality, process_payload};
+
+    /// Encodes an image as PNG screenshot, like the captures of the screenshot portal.
+    pub fn png_payload(image: impl Into<DynamicImage>) -> Payload {
+        Payload::new(
+            PathBuf::from("screenshot.png"),
+            encode_image(&image.into(), ImageFormat::Png).unwrap(),
+            "image/png".into(),
+        )
+    }
+
+    #[test]
+    pub fn test_convert_avif_to_size() {
+        let noise = RgbImage::from_fn(64, 64, |x, y| {
+            Rgb([(x * 31 + y * 17) as u8, (x * y) as u8, (x ^ y) as u8])
+        });
+        let avif = encode_image_with_quality(&noise.clone().into(), ImageFormat::Avif, 80).unwrap();
+        let profile = UploadConfig {
+            convert: Some(ConvertConfig {
+                format: OutputFormat::Avif,
+                quality: Some(80),
+            }),
+            max_size: Some(avif.len() as u64 - 1),
+            ..Default::default()
+        };
+
+        // The image is shrunk from the decoded original, as AVIF can't be decoded
+        let payload = png_payload(noise);
+        let processed = process_payload(&profile, &payload).unwrap();
+        assert_eq!(processed.payload.mime_type(), "image/avif");
+        assert!(processed.payload.size() < avif.len() as u64);
+
+        let payload = Payload::new(PathBuf::from("image.avif"), avif, "image/avif".into());
+        assert!(matches!(
+            fit_to_size(&payload, 16, 80),
+            Err(Error::UndecodableImage(mime_type)) if mime_type == "image/avif"
+        ));
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use oxipng::Options;
+
+use crate::{config::OptimizeConfig, error::Error, payload::Payload};
+
+/// Optimizes a PNG image losslessly by searching for better filters and deflate settings.
+///
+/// Payloads which are no PNG images are returned unchanged.
+///
+/// # Returns
+/// The optimized payload, never larger than the original
+pub fn optimize_png(payload: &Payload, config: &OptimizeConfig) -> Result<Payload, Error> {
+    if payload.mime_type() != "image/png" {
+        return Ok(payload.clone());
+    }
+
+    let mut options = Options::from_preset(config.level.min(6));
+    options.timeout = config.timeout.map(Duration::from_secs);
+    let bytes =
+        oxipng::optimize_from_memory(payload.bytes(), &options).map_err(|err| Error::from(err))?;
+
+    // oxipng keeps the original data if it can't be improved, this is just a safeguard
+    if bytes.len() >= payload.bytes().len() {
+        return Ok(payload.clone());
+    }
+    Ok(Payload::new(
+        payload.path().clone(),
+        bytes,
+        payload.mime_type().clone(),
+    ))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use crate::{config::OptimizeConfig, processing::tests::png_payload};
+
+    use super::optimize_png;
+
+    #[test]
+    pub fn test_optimize_png() {
+        let image = RgbaImage::from_fn(64, 64, |x, _| Rgba([(x * 4) as u8, 0, 0, 255]));
+        let payload = png_payload(image.clone());
+
+        let optimized = optimize_png(&payload, &OptimizeConfig::default()).unwrap();
+        assert!(optimized.size() < payload.size());
+        assert_eq!(
+            image::load_from_memory(optimized.bytes())
+                .unwrap()
+                .to_rgba8(),
+            image
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, Rgba, RgbaImage,
+};
+
+use crate::{config::RedactionStyle, error::Error, payload::Payload, processing::Region};
+
+use super::{decode_image, encode_image};
+
+// Pixelation blocks are at least this large, smaller blocks keep text readable
+const MIN_BLOCK_SIZE: u32 = 10;
+// Number of blocks along the shorter side of a region
+const BLOCKS_PER_SIDE: u32 = 8;
+const SOLID_COLOR: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// Redacts regions of an image, e.g. to hide tokens or email addresses.
+///
+/// The image is always re-encoded, which also drops all metadata like EXIF thumbnails.
+///
+/// # Returns
+/// The redacted payload, keeping the format of the original
+pub fn redact_payload(
+    payload: &Payload,
+    regions: &[Region],
+    style: RedactionStyle,
+) -> Result<Payload, Error> {
+    // Fails for anything but images, sharing an unredacted file is never an option
+    let format = image::guess_format(payload.bytes()).map_err(|err| Error::from(err))?;
+    let image = decode_image(payload)?;
+
+    let has_alpha = image.color().has_alpha();
+    let mut redacted = image.to_rgba8();
+    for region in regions {
+        redact_region(&mut redacted, region, style);
+    }
+    let redacted = match has_alpha {
+        true => DynamicImage::ImageRgba8(redacted),
+        false => DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(redacted).to_rgb8()),
+    };
+
+    Ok(Payload::new(
+        payload.path().clone(),
+        encode_image(&redacted, format)?,
+        payload.mime_type().clone(),
+    ))
+}
+
+/// Replaces the pixels of a region so its content can't be restored.
+///
+/// Regions exceeding the image are clipped.
+pub fn redact_region(image: &mut RgbaImage, region: &Region, style: RedactionStyle) {
+    let Some(region) = region.clip(image.width(), image.height()) else {
+        return;
+    };
+    let block_size = (region.width.min(region.height) / BLOCKS_PER_SIDE).max(MIN_BLOCK_SIZE);
+
+    match style {
+        RedactionStyle::Pixelate => pixelate(image, &region, block_size),
+        RedactionStyle::Blur => {
+            // Only the averages of the blocks are blurred, never the original pixels
+            let original =
+                imageops::crop_imm(image, region.x, region.y, region.width, region.height)
+                    .to_image();
+            let reduced = imageops::resize(
+                &original,
+                region.width.div_ceil(block_size),
+                region.height.div_ceil(block_size),
+                FilterType::Triangle,
+            );
+            let enlarged =
+                imageops::resize(&reduced, region.width, region.height, FilterType::Triangle);
+            let blurred = imageops::blur(&enlarged, block_size as f32 / 2.0);
+            imageops::replace(image, &blurred, region.x as i64, region.y as i64);
+        }
+        RedactionStyle::Solid => {
+            for y in region.y..region.y + region.height {
+                for x in region.x..region.x + region.width {
+                    image.put_pixel(x, y, SOLID_COLOR);
+                }
+            }
+        }
+    }
+}
+
+/// Replaces every block of the region with its average color.
+fn pixelate(image: &mut RgbaImage, region: &Region, block_size: u32) {
+    for block_y in (region.y..region.y + region.height).step_by(block_size as usize) {
+        for block_x in (region.x..region.x + region.width).step_by(block_size as usize) {
+            let right = (block_x + block_size).min(region.x + region.width);
+            let bottom = (block_y + block_size).min(region.y + region.height);
+
+            let mut sum = [0u64; 4];
+            for y in block_y..bottom {
+                for x in block_x..right {
+                    for (channel, value) in image.get_pixel(x, y).0.iter().enumerate() {
+                        sum[channel] += *value as u64;
+                    }
+                }
+            }
+            let count = ((right - block_x) * (bottom - block_y)) as u64;
+            let average = Rgba(sum.map(|sum| (sum / count) as u8));
+
+            for y in block_y..bottom {
+                for x in block_x..right {
+                    image.put_pixel(x, y, average);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use crate::{config::RedactionStyle, processing::Region};
+
+    use super::redact_region;
+
+    // Black and white stripes, each one pixel wide
+    fn stripes() -> RgbaImage {
+        RgbaImage::from_fn(40, 40, |x, _| match x % 2 {
+            0 => Rgba([0, 0, 0, 255]),
+            _ => Rgba([255, 255, 255, 255]),
+        })
+    }
+
+    const REGION: Region = Region {
+        x: 10,
+        y: 10,
+        width: 20,
+        height: 20,
+    };
+
+    #[test]
+    pub fn test_redact_region() {
+        for style in [
+            RedactionStyle::Pixelate,
+            RedactionStyle::Blur,
+            RedactionStyle::Solid,
+        ] {
+            let original = stripes();
+            let mut redacted = original.clone();
+            redact_region(&mut redacted, &REGION, style);
+
+            for (x, y, pixel) in redacted.enumerate_pixels() {
+                let inside = (10..30).contains(&x) && (10..30).contains(&y);
+                match inside {
+                    // No stripe survives, neighbouring pixels are (nearly) equal
+                    true => {
+                        let next = redacted.get_pixel(x.max(11) - 1, y);
+                        assert!(pixel[0].abs_diff(next[0]) < 8, "{style:?} at {x},{y}");
+                    }
+                    false => assert_eq!(pixel, original.get_pixel(x, y), "{style:?} at {x},{y}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_pixelate() {
+        let mut redacted = stripes();
+        redact_region(&mut redacted, &REGION, RedactionStyle::Pixelate);
+
+        // Blocks are 10x10 pixels, the average of the stripes is gray
+        assert_eq!(*redacted.get_pixel(10, 10), Rgba([127, 127, 127, 255]));
+        assert_eq!(*redacted.get_pixel(29, 29), Rgba([127, 127, 127, 255]));
+    }
+
+    #[test]
+    pub fn test_parse_region() {
+        assert_eq!(
+            "10, 20,30,40".parse::<Region>(),
+            Ok(Region {
+                x: 10,
+                y: 20,
+                width: 30,
+                height: 40,
+            })
+        );
+        assert!("10,20,30".parse::<Region>().is_err());
+        assert!("10,20,-30,40".parse::<Region>().is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use image::{imageops::FilterType, ImageFormat};
+
+use crate::{error::Error, payload::Payload};
+
+use super::{decode_image, encode_image_with_quality};
+
+/// Downscales an image to fit into the given dimensions, keeping its aspect ratio and format.
+///
+/// Lossy formats are re-encoded using the given quality. Payloads which are no images or
+/// already fit are returned unchanged.
+///
+/// # Returns
+/// The payload fitting into the dimensions
+pub fn fit_dimensions(
+    payload: &Payload,
+    max_width: u32,
+    max_height: u32,
+    quality: u8,
+) -> Result<Payload, Error> {
+    let Some(format) = ImageFormat::from_mime_type(payload.mime_type()) else {
+        return Ok(payload.clone());
+    };
+
+    let image = decode_image(payload)?;
+    if image.width() <= max_width && image.height() <= max_height {
+        return Ok(payload.clone());
+    }
+    log::info!(
+        "Downscaling {}x{} image to fit into {max_width}x{max_height}",
+        image.width(),
+        image.height()
+    );
+    let image = image.resize(max_width, max_height, FilterType::Lanczos3);
+
+    Ok(Payload::new(
+        payload.path().clone(),
+        encode_image_with_quality(&image, format, quality)?,
+        payload.mime_type().clone(),
+    ))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    use crate::processing::tests::png_payload;
+
+    use super::fit_dimensions;
+
+    #[test]
+    pub fn test_fit_dimensions() {
+        let image = RgbaImage::from_pixel(200, 100, Rgba([0, 0, 255, 255]));
+        let payload = png_payload(image);
+
+        let resized = fit_dimensions(&payload, 50, u32::MAX, 80).unwrap();
+        let decoded = image::load_from_memory(resized.bytes()).unwrap();
+        assert_eq!(decoded.dimensions(), (50, 25));
+        assert_eq!(resized.mime_type(), "image/png");
+
+        let unchanged = fit_dimensions(&payload, 200, 100, 80).unwrap();
+        assert_eq!(unchanged.bytes(), payload.bytes());
+    }
+}
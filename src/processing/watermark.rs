@@ -0,0 +1,322 @@
+use std::path::Path;
+
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use chrono::{DateTime, Local};
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, ImageFormat, Rgba, RgbaImage,
+};
+
+use crate::{
+    config::{WatermarkConfig, WatermarkPosition},
+    error::Error,
+    payload::Payload,
+    template::expand_template,
+};
+
+use super::{decode_image, encode_image_with_quality};
+
+// Font used for texts if no font is configured, DejaVu Sans as installed by most distributions
+pub const DEFAULT_FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+
+// Font size used to measure the width of the text before scaling it
+const REFERENCE_FONT_SIZE: f32 = 100.0;
+// Text smaller than this is unreadable
+const MIN_FONT_SIZE: f32 = 8.0;
+
+/// Draws the configured logo and text into a corner of an image.
+///
+/// Payloads which are no images are returned unchanged, lossy formats are encoded with the
+/// given quality (1-100).
+///
+/// # Returns
+/// The watermarked payload, keeping the format of the original
+pub fn apply_watermark(
+    payload: &Payload,
+    config: &WatermarkConfig,
+    quality: u8,
+) -> Result<Payload, Error> {
+    let Some(format) = ImageFormat::from_mime_type(payload.mime_type()) else {
+        return Ok(payload.clone());
+    };
+
+    let image = decode_image(payload)?;
+    let width = (image.width() as f32 * config.scale.clamp(0.0, 1.0)).round() as u32;
+    let mut layers = Vec::new();
+    if let Some(path) = &config.image {
+        let logo = image::open(path).map_err(|err| Error::from(err))?;
+        layers.push(
+            logo.resize(width.max(1), u32::MAX, FilterType::Lanczos3)
+                .to_rgba8(),
+        );
+    }
+    if let Some(template) = &config.text {
+        let font = load_font(config.font.as_deref())?;
+        let text = watermark_text(template, payload, &current_user(), Local::now());
+        if let Some(layer) = render_text(&font, &text, width as f32, config.color) {
+            layers.push(layer);
+        }
+    }
+    if layers.is_empty() {
+        return Ok(payload.clone());
+    }
+
+    let mut watermark = stack_layers(&layers, config.position, config.margin / 2);
+    let opacity = config.opacity.clamp(0.0, 1.0);
+    for pixel in watermark.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+
+    let margin = config.margin as i64;
+    let (free_x, free_y) = (
+        image.width() as i64 - watermark.width() as i64 - margin,
+        image.height() as i64 - watermark.height() as i64 - margin,
+    );
+    let (x, y) = match config.position {
+        WatermarkPosition::TopLeft => (margin, margin),
+        WatermarkPosition::TopRight => (free_x, margin),
+        WatermarkPosition::BottomLeft => (margin, free_y),
+        WatermarkPosition::BottomRight => (free_x, free_y),
+    };
+
+    let has_alpha = image.color().has_alpha();
+    let mut watermarked = image.to_rgba8();
+    imageops::overlay(&mut watermarked, &watermark, x, y);
+    let watermarked = match has_alpha {
+        true => DynamicImage::ImageRgba8(watermarked),
+        false => DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(watermarked).to_rgb8()),
+    };
+
+    Ok(Payload::new(
+        payload.path().clone(),
+        encode_image_with_quality(&watermarked, format, quality)?,
+        payload.mime_type().clone(),
+    ))
+}
+
+/// Loads the given font or the default font.
+///
+/// # Returns
+/// The loaded font
+pub fn load_font(path: Option<&Path>) -> Result<FontVec, Error> {
+    let path = path.unwrap_or(Path::new(DEFAULT_FONT));
+    if !path.exists() {
+        return Err(Error::FontNotFound(path.display().to_string()));
+    }
+    let bytes = std::fs::read(path).map_err(|err| Error::from(err))?;
+    FontVec::try_from_vec(bytes).map_err(|err| Error::from(err))
+}
+
+/// Expands the text template of a watermark.
+///
+/// # Returns
+/// The text with the user, the time of the upload and the payload variables inserted
+fn watermark_text(template: &str, payload: &Payload, user: &str, now: DateTime<Local>) -> String {
+    let mut variables = payload.template_variables();
+    variables.insert("user".into(), user.to_string());
+    variables.insert(
+        "timestamp".into(),
+        now.format("%Y-%m-%d %H:%M:%S").to_string(),
+    );
+    variables.insert("date".into(), now.format("%Y-%m-%d").to_string());
+    expand_template(template, &variables)
+}
+
+/// Renders a single line of text onto a transparent image, sized to fill the given width.
+///
+/// # Returns
+/// The rendered text or `None` if the text is empty
+fn render_text(font: &FontVec, text: &str, width: f32, color: [u8; 3]) -> Option<RgbaImage> {
+    let reference_width = text_width(font, text, PxScale::from(REFERENCE_FONT_SIZE));
+    if reference_width <= 0.0 {
+        return None;
+    }
+    let size = (REFERENCE_FONT_SIZE * width / reference_width).max(MIN_FONT_SIZE);
+    let scaled = font.as_scaled(PxScale::from(size));
+
+    let mut canvas = RgbaImage::new(
+        text_width(font, text, scaled.scale()).ceil() as u32,
+        scaled.height().ceil() as u32,
+    );
+    let mut caret = point(0.0, scaled.ascent());
+    let mut previous = None;
+    for character in text.chars() {
+        let id = scaled.glyph_id(character);
+        if let Some(previous) = previous {
+            caret.x += scaled.kern(previous, id);
+        }
+        previous = Some(id);
+
+        let glyph = id.with_scale_and_position(scaled.scale(), caret);
+        caret.x += scaled.h_advance(id);
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+
+        let bounds = outline.px_bounds();
+        outline.draw(|x, y, coverage| {
+            let (x, y) = (
+                bounds.min.x as i64 + x as i64,
+                bounds.min.y as i64 + y as i64,
+            );
+            if x < 0 || y < 0 || x >= canvas.width() as i64 || y >= canvas.height() as i64 {
+                return;
+            }
+            let pixel = canvas.get_pixel_mut(x as u32, y as u32);
+            let alpha = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+            *pixel = Rgba([color[0], color[1], color[2], pixel[3].max(alpha)]);
+        });
+    }
+
+    Some(canvas)
+}
+
+/// Measures the advance width of a single line of text.
+fn text_width(font: &FontVec, text: &str, scale: PxScale) -> f32 {
+    let scaled = font.as_scaled(scale);
+    let mut previous = None;
+    text.chars().fold(0.0, |width, character| {
+        let id = scaled.glyph_id(character);
+        let kern = previous.map_or(0.0, |previous| scaled.kern(previous, id));
+        previous = Some(id);
+        width + kern + scaled.h_advance(id)
+    })
+}
+
+/// Stacks the layers vertically, aligned to the side of the watermark position.
+///
+/// # Returns
+/// A transparent image containing all layers
+fn stack_layers(layers: &[RgbaImage], position: WatermarkPosition, gap: u32) -> RgbaImage {
+    let width = layers
+        .iter()
+        .map(|layer| layer.width())
+        .max()
+        .unwrap_or_default();
+    let height = layers.iter().map(|layer| layer.height()).sum::<u32>()
+        + gap * (layers.len() as u32).saturating_sub(1);
+
+    let mut stacked = RgbaImage::new(width, height);
+    let mut y = 0;
+    for layer in layers {
+        let x = match position {
+            WatermarkPosition::TopLeft | WatermarkPosition::BottomLeft => 0,
+            WatermarkPosition::TopRight | WatermarkPosition::BottomRight => width - layer.width(),
+        };
+        imageops::replace(&mut stacked, layer, x as i64, y as i64);
+        y += layer.height() + gap;
+    }
+    stacked
+}
+
+/// Resolves the name of the current user from the environment.
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::PathBuf;
+
+    use chrono::{Local, TimeZone};
+    use image::{DynamicImage, GenericImageView, Rgb, RgbImage, Rgba, RgbaImage};
+
+    use crate::{
+        config::{WatermarkConfig, WatermarkPosition},
+        error::Error,
+        payload::Payload,
+        processing::{encode_jpeg, tests::png_payload},
+    };
+
+    use super::{apply_watermark, watermark_text};
+
+    #[test]
+    pub fn test_apply_watermark() {
+        let logo_path = std::env::temp_dir().join("shareshot-watermark-logo.png");
+        RgbaImage::from_pixel(10, 10, Rgba([255, 0, 0, 255]))
+            .save(&logo_path)
+            .unwrap();
+
+        let image = RgbaImage::from_pixel(100, 50, Rgba([0, 0, 255, 255]));
+        let payload = png_payload(image);
+
+        let config = WatermarkConfig {
+            image: Some(logo_path.clone()),
+            position: WatermarkPosition::BottomRight,
+            opacity: 1.0,
+            scale: 0.2,
+            margin: 5,
+            ..Default::default()
+        };
+        let watermarked = apply_watermark(&payload, &config, 80).unwrap();
+        let decoded = image::load_from_memory(watermarked.bytes()).unwrap();
+        std::fs::remove_file(logo_path).unwrap();
+
+        // The logo is scaled to 20x20 and placed 5 pixels away from the bottom right corner
+        assert_eq!(decoded.dimensions(), (100, 50));
+        assert_eq!(decoded.get_pixel(94, 44), Rgba([255, 0, 0, 255]));
+        assert_eq!(decoded.get_pixel(75, 25), Rgba([255, 0, 0, 255]));
+        assert_eq!(decoded.get_pixel(74, 44), Rgba([0, 0, 255, 255]));
+        assert_eq!(decoded.get_pixel(95, 45), Rgba([0, 0, 255, 255]));
+        assert_eq!(decoded.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    pub fn test_watermark_text() {
+        let payload = png_payload(RgbaImage::new(1, 1));
+        let now = Local.with_ymd_and_hms(2026, 10, 19, 14, 30, 5).unwrap();
+
+        assert_eq!(
+            watermark_text(
+                "© {user}, {date} ({timestamp}) {file_name}",
+                &payload,
+                "jane",
+                now
+            ),
+            "© jane, 2026-10-19 (2026-10-19 14:30:05) screenshot.png"
+        );
+    }
+
+    #[test]
+    pub fn test_missing_font() {
+        let payload = png_payload(RgbaImage::new(100, 50));
+        let config = WatermarkConfig {
+            text: Some("{user}".into()),
+            font: Some("/nonexistent/font.ttf".into()),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            apply_watermark(&payload, &config, 80),
+            Err(Error::FontNotFound(path)) if path == "/nonexistent/font.ttf"
+        ));
+    }
+
+    #[test]
+    pub fn test_watermark_quality() {
+        let logo_path = std::env::temp_dir().join("shareshot-watermark-quality.png");
+        RgbaImage::from_pixel(10, 10, Rgba([255, 0, 0, 255]))
+            .save(&logo_path)
+            .unwrap();
+        let noise = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| {
+            Rgb([(x * 31 + y * 17) as u8, (x * y) as u8, (x ^ y) as u8])
+        }));
+        let payload = Payload::new(
+            PathBuf::from("screenshot.jpg"),
+            encode_jpeg(&noise, 95).unwrap(),
+            "image/jpeg".into(),
+        );
+        let config = WatermarkConfig {
+            image: Some(logo_path.clone()),
+            ..Default::default()
+        };
+
+        let high = apply_watermark(&payload, &config, 95).unwrap();
+        let low = apply_watermark(&payload, &config, 40).unwrap();
+        std::fs::remove_file(logo_path).unwrap();
+        assert_eq!(high.mime_type(), "image/jpeg");
+        assert!(high.size() > low.size());
+    }
+}
@@ -0,0 +1,38 @@
+use std::collections::BTreeMap;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+// Detects {variable}
+static VARIABLE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{([a-zA-Z0-9_.]+)\}").unwrap());
+
+/// Replaces all `{variable}` placeholders in a template with their values.
+///
+/// Placeholders of unknown variables are left untouched.
+///
+/// # Returns
+/// The expanded template
+pub fn expand_template(template: &str, variables: &BTreeMap<String, String>) -> String {
+    VARIABLE_REGEX
+        .replace_all(template, |captures: &Captures| match variables.get(&captures[1]) {
+            Some(value) => value.clone(),
+            None => captures[0].to_string(),
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::BTreeMap;
+
+    use super::expand_template;
+
+    #[test]
+    pub fn test_expand_template() {
+        let variables = BTreeMap::from([("url".to_string(), "https://example.com".to_string())]);
+        assert_eq!(
+            expand_template("{\"target\": \"{url}\", \"other\": \"{unknown}\"}", &variables),
+            "{\"target\": \"https://example.com\", \"other\": \"{unknown}\"}".to_string()
+        );
+    }
+}
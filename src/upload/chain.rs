@@ -0,0 +1,108 @@
+use reqwest::RequestBuilder;
+
+use crate::{
+    config::{HttpConfig, UploadStrategy},
+    error::Error,
+    parser::parse_url,
+    payload::Payload,
+};
+
+use super::{graphql::check_graphql_errors, request::StepRequest};
+
+/// Uploads a payload by executing the request chain of the given upload server.
+///
+/// Every step may extract values from its response which can be referenced by all
+/// following steps. The response of the last step is parsed using the configured url parser.
+///
+/// # Returns
+/// The url to the uploaded payload
+pub async fn upload_chain(
+    config: &HttpConfig,
+    payload: &Payload,
+    rate_limit: Option<u64>,
+) -> Result<String, Error> {
+    let mut variables = payload.template_variables();
+    let mut text = String::new();
+
+    for (index, step) in config.steps.iter().enumerate() {
+        let request = StepRequest::new(step, payload, &variables).with_rate_limit(rate_limit);
+        let response = RequestBuilder::from(request)
+            .send()
+            .await
+            .map_err(|err| Error::from(err))?;
+
+        let status = response.status();
+        text = response.text().await.map_err(|err| Error::from(err))?;
+        if !status.is_success() {
+            return Err(Error::NonOkStatusCode(
+                status.to_string(),
+                format!("step {}: {text}", index + 1),
+            ));
+        }
+        if step.upload_strategy == Some(UploadStrategy::GraphQl) {
+            check_graphql_errors(&text)?;
+        }
+
+        for (name, statement) in &step.extract {
+            variables.insert(name.clone(), parse_url(&text, statement)?);
+        }
+    }
+
+    parse_url(&text, &config.url_parser)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    use crate::{
+        config::{HttpConfig, RequestMethod, RequestStep},
+        payload::Payload,
+        upload::tests::{response, serve},
+    };
+
+    use super::upload_chain;
+
+    #[tokio::test]
+    pub async fn test_step_templating() {
+        let (url, requests) = serve(vec![
+            response("200 OK", "application/json", r#"{"id": "abc123"}"#),
+            response(
+                "200 OK",
+                "application/json",
+                r#"{"url": "https://example.com/abc123.png"}"#,
+            ),
+        ])
+        .await;
+
+        let config = HttpConfig {
+            steps: vec![
+                RequestStep {
+                    url: format!("{url}/session"),
+                    request_method: RequestMethod::Get,
+                    extract: BTreeMap::from([("id".to_string(), "$json:id$".to_string())]),
+                    ..Default::default()
+                },
+                RequestStep {
+                    url: format!("{url}/upload/{{id}}"),
+                    request_method: RequestMethod::Post,
+                    headers: BTreeMap::from([("X-Session".to_string(), "{id}".to_string())]),
+                    body: Some("name={file_name}&session={id}".into()),
+                    ..Default::default()
+                },
+            ],
+            url_parser: "$json:url$".into(),
+            ..Default::default()
+        };
+        let payload = Payload::new(PathBuf::from("image.png"), vec![0; 4], "image/png".into());
+
+        let uploaded = upload_chain(&config, &payload, None).await.unwrap();
+        assert_eq!(uploaded, "https://example.com/abc123.png");
+
+        let requests = requests.await.unwrap();
+        assert!(requests[0].starts_with("get /session "));
+        assert!(requests[1].starts_with("post /upload/abc123 "));
+        assert!(requests[1].contains("x-session: abc123"));
+        assert!(requests[1].ends_with("name=image.png&session=abc123"));
+    }
+}
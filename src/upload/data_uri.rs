@@ -0,0 +1,123 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{
+    config::DataUriConfig,
+    error::Error,
+    payload::Payload,
+    processing::{compress::fit_to_size, DEFAULT_QUALITY},
+};
+
+use super::uploader::{Capabilities, Uploader};
+
+/// Encodes images into `data:` URIs instead of uploading them.
+pub struct DataUriUploader<'a> {
+    config: &'a DataUriConfig,
+}
+
+impl<'a> DataUriUploader<'a> {
+    pub fn new(config: &'a DataUriConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl Uploader for DataUriUploader<'_> {
+    async fn upload(&self, payload: &Payload, _rate_limit: Option<u64>) -> Result<String, Error> {
+        let payload = match self.config.max_size {
+            Some(max_size) => {
+                // Recompressed images keep their format and therefore the prefix
+                let prefix = data_uri_prefix(payload.mime_type()).len() as u64;
+                let max_bytes = max_size.saturating_sub(prefix) / 4 * 3;
+                fit_to_size(
+                    payload,
+                    max_bytes,
+                    self.config.quality.unwrap_or(DEFAULT_QUALITY),
+                )?
+            }
+            None => payload.clone(),
+        };
+
+        Ok(format!(
+            "{}{}",
+            data_uri_prefix(payload.mime_type()),
+            STANDARD.encode(payload.bytes())
+        ))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            delete: false,
+            rate_limit: false,
+            local: true,
+        }
+    }
+}
+
+fn data_uri_prefix(mime_type: &str) -> String {
+    format!("data:{mime_type};base64,")
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::PathBuf;
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use image::{Rgb, RgbImage};
+
+    use crate::{
+        config::DataUriConfig, error::Error, payload::Payload, processing::tests::png_payload,
+        upload::uploader::Uploader,
+    };
+
+    use super::DataUriUploader;
+
+    #[tokio::test]
+    pub async fn test_data_uri() {
+        let config = DataUriConfig::default();
+        let payload = Payload::new(
+            PathBuf::from("image.png"),
+            b"abc".to_vec(),
+            "image/png".into(),
+        );
+        let uri = DataUriUploader::new(&config).upload(&payload, None).await;
+        assert_eq!(uri.unwrap(), "data:image/png;base64,YWJj");
+    }
+
+    #[tokio::test]
+    pub async fn test_max_size() {
+        let noise = RgbImage::from_fn(128, 128, |x, y| {
+            Rgb([(x * 31 + y * 17) as u8, (x * y) as u8, (x ^ y) as u8])
+        });
+        let payload = png_payload(noise);
+        let config = DataUriConfig {
+            max_size: Some(payload.size() / 2),
+            ..Default::default()
+        };
+
+        // The prefix and the base64 overhead are part of the limit
+        let uri = DataUriUploader::new(&config)
+            .upload(&payload, None)
+            .await
+            .unwrap();
+        assert!(uri.starts_with("data:image/png;base64,"));
+        assert!(uri.len() as u64 <= payload.size() / 2);
+        let data = STANDARD
+            .decode(&uri["data:image/png;base64,".len()..])
+            .unwrap();
+        assert!(image::load_from_memory(&data).unwrap().width() < 128);
+
+        let text = Payload::new(
+            PathBuf::from("notes.txt"),
+            vec![b'a'; 100],
+            "text/plain".into(),
+        );
+        let config = DataUriConfig {
+            max_size: Some(100),
+            ..Default::default()
+        };
+        let result = DataUriUploader::new(&config).upload(&text, None).await;
+        // 100 bytes minus the prefix of 23 bytes hold 19 base64 blocks of 3 bytes each
+        assert!(matches!(result, Err(Error::PayloadTooLarge(100, 57))));
+    }
+}
@@ -0,0 +1,90 @@
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+
+// The multipart field of the file, mapped to the `$file` variable of the mutation
+pub const GRAPHQL_FILE_FIELD: &str = "0";
+pub const GRAPHQL_FILE_MAP: &str = r#"{"0": ["variables.file"]}"#;
+
+/// Builds the `operations` field of a GraphQL multipart request.
+///
+/// The mutation has to declare the uploaded file as `$file` variable, which is set to `null`
+/// as required by the GraphQL multipart request specification.
+///
+/// # Returns
+/// The operations as JSON string
+pub fn graphql_operations(query: &str, variables: &str) -> String {
+    let mut variables = match serde_json::from_str::<Value>(variables) {
+        Ok(Value::Object(variables)) => variables,
+        _ => {
+            log::warn!("The GraphQL variables are not a JSON object, sending no variables");
+            Map::new()
+        }
+    };
+    variables.insert("file".into(), Value::Null);
+
+    serde_json::json!({
+        "query": query,
+        "variables": variables,
+    })
+    .to_string()
+}
+
+/// Checks a GraphQL response for errors, which are reported with a successful status code.
+pub fn check_graphql_errors(response: &str) -> Result<(), Error> {
+    let Ok(response) = serde_json::from_str::<Value>(response) else {
+        return Ok(());
+    };
+
+    match response.get("errors").and_then(|errors| errors.as_array()) {
+        Some(errors) if !errors.is_empty() => Err(Error::GraphQl(
+            errors
+                .iter()
+                .map(|error| {
+                    error
+                        .get("message")
+                        .and_then(|message| message.as_str())
+                        .map(|message| message.to_string())
+                        .unwrap_or_else(|| error.to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::Value;
+
+    use super::{check_graphql_errors, graphql_operations};
+
+    #[test]
+    pub fn test_graphql_operations() {
+        let operations = graphql_operations(
+            "mutation ($file: Upload!, $album: ID) { upload(file: $file, album: $album) { url } }",
+            r#"{"album": "42"}"#,
+        );
+        let operations = serde_json::from_str::<Value>(&operations).unwrap();
+
+        assert_eq!(operations["variables"]["album"], "42");
+        assert_eq!(operations["variables"]["file"], Value::Null);
+        assert!(operations["query"]
+            .as_str()
+            .unwrap()
+            .starts_with("mutation"));
+    }
+
+    #[test]
+    pub fn test_graphql_errors() {
+        assert!(check_graphql_errors(r#"{"data": {"upload": {"url": "a"}}}"#).is_ok());
+        assert!(check_graphql_errors(r#"{"data": null, "errors": []}"#).is_ok());
+        assert_eq!(
+            check_graphql_errors(r#"{"errors": [{"message": "File too large"}]}"#)
+                .unwrap_err()
+                .to_string(),
+            "GraphQL request failed: File too large"
+        );
+    }
+}
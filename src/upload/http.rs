@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use reqwest::RequestBuilder;
+
+use crate::{
+    config::{HttpConfig, UploadStrategy},
+    error::Error,
+    parser::parse_url,
+    payload::Payload,
+};
+
+use super::{
+    chain::upload_chain,
+    graphql::check_graphql_errors,
+    preview::RequestPreview,
+    request::{ImageUploadRequest, StepRequest},
+    uploader::{Capabilities, Uploader},
+};
+
+/// Uploads images to a server using one or multiple HTTP requests.
+pub struct HttpUploader<'a> {
+    config: &'a HttpConfig,
+}
+
+impl<'a> HttpUploader<'a> {
+    pub fn new(config: &'a HttpConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl Uploader for HttpUploader<'_> {
+    async fn upload(&self, payload: &Payload, rate_limit: Option<u64>) -> Result<String, Error> {
+        if !self.config.steps.is_empty() {
+            return upload_chain(self.config, payload, rate_limit).await;
+        }
+
+        let response = RequestBuilder::from(
+            ImageUploadRequest::new(self.config, payload).with_rate_limit(rate_limit),
+        )
+        .send()
+        .await
+        .map_err(|err| Error::from(err))?;
+
+        let status = response.status();
+        let text = response.text().await.map_err(|err| Error::from(err))?;
+        if !status.is_success() {
+            return Err(Error::NonOkStatusCode(status.to_string(), text));
+        }
+        if self.config.upload_strategy == UploadStrategy::GraphQl {
+            check_graphql_errors(&text)?;
+        }
+
+        Ok(parse_url(&text, &self.config.url_parser)?)
+    }
+
+    fn preview(&self, payload: &Payload) -> Result<Vec<RequestPreview>, Error> {
+        if self.config.steps.is_empty() {
+            return Ok(vec![
+                ImageUploadRequest::new(self.config, payload).preview()?
+            ]);
+        }
+
+        // Values extracted from responses are unknown and stay unexpanded
+        let variables = payload.template_variables();
+        self.config
+            .steps
+            .iter()
+            .map(|step| StepRequest::new(step, payload, &variables).preview())
+            .collect()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            delete: false,
+            rate_limit: true,
+            local: false,
+        }
+    }
+}
@@ -1,30 +1,617 @@
+use std::{borrow::Cow, time::Duration};
+
 use once_cell::sync::Lazy;
-use reqwest::{Client, RequestBuilder};
+use reqwest::Client;
 
-use crate::{application::CONFIG, error::Error, image::Image, parser::parse_url};
+use crate::{
+    application::CONFIG,
+    config::{ContentKind, ShareShotConfig, UploadConfig, PRIMARY_PROFILE},
+    dbus::network::is_unmetered,
+    encryption::encrypt_payload,
+    error::Error,
+    history::find_upload,
+    payload::{mime_matches, Payload},
+    processing::process_payload,
+};
 
-use self::request::ImageUploadRequest;
+use self::{
+    preview::RequestPreview,
+    shortener::shorten_url,
+    uploader::{create_uploader, Uploader},
+    verify::verify_url,
+};
 
+pub mod chain;
+pub mod data_uri;
+pub mod graphql;
+pub mod http;
+pub mod paste;
+pub mod preview;
 pub mod request;
+pub mod shortener;
+pub mod throttle;
+pub mod uploader;
+pub mod verify;
 
 pub(crate) static CLIENT: Lazy<Client> = Lazy::new(|| Client::new());
 
-/// Uploads an image to the in the config-defined upload server.
+/// Stores data about a successful upload.
+pub struct UploadResult {
+    url: String,
+    short_url: Option<String>,
+    profile: String,
+    reused: bool,
+    local: bool,
+    saved_bytes: Option<u64>,
+}
+
+impl UploadResult {
+    pub fn url(&self) -> &String {
+        &self.url
+    }
+
+    pub fn short_url(&self) -> &Option<String> {
+        &self.short_url
+    }
+
+    /// The url which should be shared, the short url if the uploaded url was shortened.
+    pub fn shared_url(&self) -> &String {
+        self.short_url.as_ref().unwrap_or(&self.url)
+    }
+
+    /// The name of the profile which received the upload.
+    pub fn profile(&self) -> &String {
+        &self.profile
+    }
+
+    /// Whether the url of a previous upload of the same payload was reused.
+    pub fn reused(&self) -> bool {
+        self.reused
+    }
+
+    /// Whether the url was created without uploading, e.g. a data URI.
+    pub fn local(&self) -> bool {
+        self.local
+    }
+
+    /// The bytes saved by optimizing the payload before uploading.
+    pub fn saved_bytes(&self) -> Option<u64> {
+        self.saved_bytes
+    }
+}
+
+/// Uploads a payload to the in the config-defined upload server.
+///
+/// If the upload server is unreachable after all retries, the configured fallback
+/// profiles are tried in order. Failed verifications are not retried on the fallbacks, the
+/// payload was already uploaded and would be left behind on the first server.
+/// Profiles with deduplication enabled reuse the url of a previous upload of the same payload,
+/// unless `force_upload` is set.
+///
+/// # Returns
+/// The url to the uploaded payload and the profile which received it
+pub async fn upload_payload(payload: &Payload, force_upload: bool) -> Result<UploadResult, Error> {
+    // Uploads may take minutes, the settings must stay editable in the meantime
+    let config = CONFIG.lock().await.clone();
+    upload_payload_with(&config, payload, force_upload, create_uploader).await
+}
+
+/// Uploads a payload like `upload_payload`, using the given function to create the uploader
+/// of each profile.
+///
+/// # Returns
+/// The url to the uploaded payload and the profile which received it
+pub async fn upload_payload_with<F>(
+    config: &ShareShotConfig,
+    payload: &Payload,
+    force_upload: bool,
+    create_uploader: F,
+) -> Result<UploadResult, Error>
+where
+    F: Fn(&UploadConfig) -> Box<dyn Uploader + '_>,
+{
+    // Text pastes start at the paste profile, which has its own fallbacks
+    let start = match payload.is_text() {
+        true => config.paste_profile.as_deref().unwrap_or(PRIMARY_PROFILE),
+        false => PRIMARY_PROFILE,
+    };
+    let chain = config.fallback_chain_from(start)?;
+    let mut rejections = Vec::new();
+    // Reported instead of the rejections if a profile accepted the payload but failed
+    let mut last_error = None;
+    // Deduplication and the history always refer to the original payload
+    let hash = payload.hash();
+
+    for (index, (name, profile)) in chain.iter().enumerate() {
+        // Checked before processing, as the hash is of the original payload
+        if profile.deduplicate && !force_upload {
+            match find_upload(name, &hash) {
+                Ok(Some(entry)) => {
+                    log::info!("File was already uploaded to profile '{name}', reusing url");
+                    return Ok(UploadResult {
+                        url: entry.url,
+                        short_url: entry.short_url,
+                        profile: name.to_string(),
+                        reused: true,
+                        local: false,
+                        saved_bytes: None,
+                    });
+                }
+                Ok(None) => {}
+                Err(err) => log::warn!("Failed to look up previous uploads: {err}"),
+            }
+        }
+
+        let processed = match process_payload(profile, payload) {
+            Ok(processed) => processed,
+            Err(err) if index + 1 < chain.len() => {
+                log::warn!("Processing for profile '{name}' failed, trying fallback: {err}");
+                last_error = Some(err);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        let payload = processed.payload.as_ref();
+
+        if let Err(reason) = check_payload(profile, payload) {
+            log::info!("Profile '{name}' does not accept the file: {reason}");
+            rejections.push(format!("'{name}' {reason}"));
+            continue;
+        }
+
+        let uploader = create_uploader(profile);
+        let capabilities = uploader.capabilities();
+
+        // Local urls are never sent to a server, so they are neither encrypted nor shortened
+        let encrypted = match profile.encrypt && !capabilities.local {
+            true => Some(encrypt_payload(payload)?),
+            false => None,
+        };
+        let upload = encrypted.as_ref().map_or(payload, |(encrypted, _)| encrypted);
+
+        let rate_limit = match capabilities.rate_limit {
+            true => effective_rate_limit(config, profile).await,
+            false => None,
+        };
+        let result =
+            upload_with_retries(uploader.as_ref(), profile.retries, upload, rate_limit).await;
+        match (&result, &profile.verify) {
+            (Ok(url), Some(verify)) if !capabilities.local => {
+                verify_url(verify, url, upload.mime_type())
+                    .await
+                    .inspect_err(|_| log::error!("Upload to '{name}' is orphaned at {url}"))?;
+            }
+            _ => {}
+        }
+
+        match result {
+            Ok(url) => {
+                let short_url = match &profile.shortener {
+                    Some(shortener) if !capabilities.local => shorten_url(shortener, &url)
+                        .await
+                        .inspect_err(|err| log::warn!("Failed to shorten url: {err}"))
+                        .ok(),
+                    _ => None,
+                };
+
+                // The key is only appended after shortening to never send it to any server
+                let fragment = encrypted.map(|(_, key)| format!("#{key}")).unwrap_or_default();
+                return Ok(UploadResult {
+                    url: format!("{url}{fragment}"),
+                    short_url: short_url.map(|short_url| format!("{short_url}{fragment}")),
+                    profile: name.to_string(),
+                    reused: false,
+                    local: capabilities.local,
+                    saved_bytes: processed.saved_bytes,
+                });
+            }
+            Err(err) if index + 1 < chain.len() => {
+                log::warn!("Upload to profile '{name}' failed, trying fallback: {err}");
+                last_error = Some(err);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(last_error.unwrap_or(Error::PayloadRejected(rejections.join(", "))))
+}
+
+/// Builds the requests an upload to the given profile would make, without sending them.
+///
+/// # Returns
+/// The requests in the order they would be sent, with all secrets masked
+pub async fn preview_upload(profile: &str, payload: &Payload) -> Result<Vec<RequestPreview>, Error> {
+    let config = CONFIG.lock().await;
+    let profile = config
+        .profile(profile)
+        .ok_or(Error::UnknownProfile(profile.to_string()))?;
+    preview_profile(profile, payload)
+}
+
+/// Builds the requests an upload using the given profile configuration would make.
+///
+/// # Returns
+/// The requests in the order they would be sent, with all secrets masked
+fn preview_profile(
+    profile: &UploadConfig,
+    payload: &Payload,
+) -> Result<Vec<RequestPreview>, Error> {
+    let processed = process_payload(profile, payload)?;
+    let encrypted = match profile.encrypt {
+        true => Some(encrypt_payload(&processed.payload)?),
+        false => None,
+    };
+    let upload = encrypted
+        .as_ref()
+        .map_or(processed.payload.as_ref(), |(encrypted, _)| encrypted);
+    let uploader = create_uploader(profile);
+    let mut requests = uploader.preview(upload)?;
+
+    // The processed or encrypted file only exists in memory
+    if matches!(processed.payload, Cow::Owned(_)) || encrypted.is_some() {
+        for request in &mut requests {
+            request.use_original_file(payload.path());
+        }
+    }
+    Ok(requests)
+}
+
+/// Uploads a payload using a single uploader, retrying failed attempts as configured.
+///
+/// # Returns
+/// The url to the uploaded payload
+async fn upload_with_retries(
+    uploader: &dyn Uploader,
+    retries: u32,
+    payload: &Payload,
+    rate_limit: Option<u64>,
+) -> Result<String, Error> {
+    for attempt in 0..=retries {
+        if attempt > 0 {
+            tokio::time::sleep(Duration::from_secs(attempt as u64)).await;
+        }
+
+        match uploader.upload(payload, rate_limit).await {
+            Ok(url) => return Ok(url),
+            Err(err) if attempt < retries => {
+                log::warn!("Upload attempt {}/{} failed: {err}", attempt + 1, retries + 1);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    unreachable!("At least one upload attempt is always made")
+}
+
+/// Checks whether a profile accepts the payload based on its content kind, allowed types and
+/// maximum size.
+///
+/// # Returns
+/// The reason if the payload is rejected
+fn check_payload(profile: &UploadConfig, payload: &Payload) -> Result<(), String> {
+    match (profile.content, payload.is_text()) {
+        (ContentKind::Files, true) => return Err("does not accept text".into()),
+        (ContentKind::Text, false) => return Err("only accepts text".into()),
+        _ => {}
+    }
+
+    let mime_type = payload.mime_type();
+    let allowed = profile.allowed_types.is_empty()
+        || profile
+            .allowed_types
+            .iter()
+            .any(|pattern| mime_matches(pattern, mime_type));
+    if !allowed {
+        return Err(format!("does not allow {mime_type}"));
+    }
+
+    match profile.max_size {
+        Some(max_size) if payload.size() > max_size => Err(format!(
+            "only allows files up to {max_size} bytes, got {}",
+            payload.size()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Resolves the upload speed limit of a profile.
 ///
 /// # Returns
-/// The url to the uploaded image
-pub async fn upload_image(image: &Image) -> Result<String, Error> {
-    let config = &CONFIG.lock().await.upload_server;
-    let response = RequestBuilder::from(ImageUploadRequest::new(&config, image))
-        .send()
-        .await
-        .map_err(|err| Error::from(err))?;
+/// The limit in bytes per second or `None` if uploads are not limited
+async fn effective_rate_limit(config: &ShareShotConfig, profile: &UploadConfig) -> Option<u64> {
+    let rate_limit = config.rate_limit(profile)?;
+    match config.unlimited_on_unmetered {
+        true => unmetered_rate_limit(rate_limit, is_unmetered().await),
+        false => Some(rate_limit),
+    }
+}
+
+/// Lifts a rate limit if the network is unmetered, keeping it if the state is unknown.
+///
+/// # Returns
+/// The limit in bytes per second or `None` if uploads are not limited
+fn unmetered_rate_limit(rate_limit: u64, unmetered: Result<bool, Error>) -> Option<u64> {
+    match unmetered {
+        Ok(true) => None,
+        Ok(false) => Some(rate_limit),
+        Err(err) => {
+            log::warn!("Failed to check whether the network is metered: {err}");
+            Some(rate_limit)
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::PathBuf;
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    use crate::{
+        config::{
+            MetadataPolicy, OptimizeConfig, ShareShotConfig, ShortenerConfig, UploadConfig,
+            VerifyConfig, WatermarkConfig, PRIMARY_PROFILE,
+        },
+        error::Error,
+        history::{append_history, HistoryEntry},
+        payload::Payload,
+    };
+
+    use super::{
+        check_payload, preview_profile, unmetered_rate_limit, upload_payload_with,
+        uploader::{Capabilities, Uploader},
+    };
+
+    // Fails if no url is configured, otherwise returns the configured url
+    struct FakeUploader {
+        url: String,
+    }
+
+    #[async_trait]
+    impl Uploader for FakeUploader {
+        async fn upload(&self, _payload: &Payload, _rate_limit: Option<u64>) -> Result<String, Error> {
+            match self.url.is_empty() {
+                true => Err(Error::NonOkStatusCode("503".into(), String::new())),
+                false => Ok(self.url.clone()),
+            }
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+    }
+
+    fn create_fake_uploader(config: &UploadConfig) -> Box<dyn Uploader + '_> {
+        Box::new(FakeUploader {
+            url: config.http.url.clone(),
+        })
+    }
+
+    /// Formats a raw HTTP response which closes the connection.
+    pub fn response(status: &str, content_type: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    /// Serves one raw HTTP response per connection on a local port, in the given order.
+    ///
+    /// # Returns
+    /// The base url of the server and a handle resolving to all received requests, with
+    /// lowercase header names
+    pub async fn serve(responses: Vec<String>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
 
-    let status = response.status();
-    let text = response.text().await.map_err(|err| Error::from(err))?;
-    if !status.is_success() {
-        return Err(Error::NonOkStatusCode(status.to_string(), text));
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 4096];
+                // Read the head, then as much of the body as announced
+                let complete = |request: &[u8]| {
+                    let text = String::from_utf8_lossy(request).to_lowercase();
+                    let (head, body) = text.split_once("\r\n\r\n")?;
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .map_or(0, |length| length.trim().parse().unwrap());
+                    (body.len() >= length).then_some(())
+                };
+                while complete(&request).is_none() {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                requests.push(String::from_utf8_lossy(&request).to_lowercase());
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+            requests
+        });
+        (url, handle)
     }
 
-    Ok(parse_url(&text, &config.url_parser)?)
+    #[tokio::test]
+    pub async fn test_fallback() {
+        let mut config = ShareShotConfig::default();
+        config.upload_server.fallback = Some("backup".into());
+        let mut backup = UploadConfig::default();
+        backup.http.url = "https://backup.example.com/image.png".into();
+        config.profiles.insert("backup".into(), backup);
+
+        let payload = Payload::new(PathBuf::from("image.png"), vec![0; 4], "image/png".into());
+        let result = upload_payload_with(&config, &payload, false, create_fake_uploader)
+            .await
+            .unwrap();
+        assert_eq!(result.profile(), "backup");
+        assert_eq!(result.url(), "https://backup.example.com/image.png");
+    }
+
+    #[tokio::test]
+    pub async fn test_fallback_rejection() {
+        let mut config = ShareShotConfig::default();
+        config.upload_server.fallback = Some("backup".into());
+        let backup = UploadConfig {
+            allowed_types: vec!["application/pdf".into()],
+            ..Default::default()
+        };
+        config.profiles.insert("backup".into(), backup);
+
+        // The failed upload is reported instead of the rejection by the fallback
+        let payload = Payload::new(PathBuf::from("image.png"), vec![0; 4], "image/png".into());
+        let result = upload_payload_with(&config, &payload, false, create_fake_uploader).await;
+        assert!(matches!(result, Err(Error::NonOkStatusCode(..))));
+
+        config.upload_server.allowed_types = vec!["application/pdf".into()];
+        let result = upload_payload_with(&config, &payload, false, create_fake_uploader).await;
+        assert!(matches!(result, Err(Error::PayloadRejected(..))));
+    }
+
+    #[tokio::test]
+    pub async fn test_shortener_failure() {
+        let (shortener_url, _) = serve(vec![response(
+            "500 Internal Server Error",
+            "text/plain",
+            "",
+        )])
+        .await;
+        let mut config = ShareShotConfig::default();
+        config.upload_server.http.url = "https://example.com/image.png".into();
+        config.upload_server.shortener = Some(ShortenerConfig {
+            url: shortener_url,
+            ..Default::default()
+        });
+
+        // The long url is shared instead
+        let payload = Payload::new(PathBuf::from("image.png"), vec![0; 4], "image/png".into());
+        let result = upload_payload_with(&config, &payload, false, create_fake_uploader)
+            .await
+            .unwrap();
+        assert_eq!(result.short_url(), &None);
+        assert_eq!(result.shared_url(), "https://example.com/image.png");
+    }
+
+    #[tokio::test]
+    pub async fn test_deduplicate() {
+        let mut config = ShareShotConfig::default();
+        config.upload_server.http.url = "https://example.com/new.png".into();
+        config.upload_server.deduplicate = true;
+        // Processing would fail on the invalid image, reused uploads are never processed
+        config.upload_server.max_width = Some(1);
+
+        let payload = Payload::new(
+            PathBuf::from("image.png"),
+            b"test_deduplicate".to_vec(),
+            "image/png".into(),
+        );
+        let mut entry =
+            HistoryEntry::new(PRIMARY_PROFILE.into(), "https://example.com/old.png".into());
+        entry.hash = Some(payload.hash());
+        append_history(&entry).unwrap();
+
+        let result = upload_payload_with(&config, &payload, false, create_fake_uploader)
+            .await
+            .unwrap();
+        assert!(result.reused());
+        assert_eq!(result.url(), "https://example.com/old.png");
+
+        config.upload_server.max_width = None;
+        let result = upload_payload_with(&config, &payload, true, create_fake_uploader)
+            .await
+            .unwrap();
+        assert!(!result.reused());
+        assert_eq!(result.url(), "https://example.com/new.png");
+    }
+
+    #[tokio::test]
+    pub async fn test_verification_failure() {
+        let mut config = ShareShotConfig::default();
+        // Nothing listens on the port, so the verification fails immediately
+        config.upload_server.http.url = "http://127.0.0.1:9/image.png".into();
+        config.upload_server.verify = Some(VerifyConfig {
+            timeout: 0,
+            ..Default::default()
+        });
+        config.upload_server.fallback = Some("backup".into());
+        let mut backup = UploadConfig::default();
+        backup.http.url = "https://backup.example.com/image.png".into();
+        config.profiles.insert("backup".into(), backup);
+
+        let payload = Payload::new(PathBuf::from("image.png"), vec![0; 4], "image/png".into());
+        let result = upload_payload_with(&config, &payload, false, create_fake_uploader).await;
+        assert!(matches!(
+            result,
+            Err(Error::VerificationFailed(url, _)) if url == "http://127.0.0.1:9/image.png"
+        ));
+    }
+
+    #[test]
+    pub fn test_preview_placeholder() {
+        let logo = std::env::temp_dir().join("shareshot-preview-logo.png");
+        image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255]))
+            .save(&logo)
+            .unwrap();
+
+        let mut profile = UploadConfig {
+            max_width: Some(1),
+            metadata: MetadataPolicy::Strip,
+            optimize: Some(OptimizeConfig::default()),
+            watermark: Some(WatermarkConfig {
+                image: Some(logo),
+                ..Default::default()
+            }),
+            max_size: Some(1024),
+            ..Default::default()
+        };
+        profile.http.url = "https://example.com/upload".into();
+
+        let requests = preview_profile(&profile, &Payload::placeholder()).unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].warning.is_some());
+    }
+
+    #[test]
+    pub fn test_rate_limit() {
+        let config = ShareShotConfig {
+            upload_rate_limit: Some(100),
+            ..Default::default()
+        };
+        let mut profile = UploadConfig::default();
+        assert_eq!(config.rate_limit(&profile), Some(100));
+        profile.rate_limit = Some(50);
+        assert_eq!(config.rate_limit(&profile), Some(50));
+
+        assert_eq!(unmetered_rate_limit(50, Ok(true)), None);
+        assert_eq!(unmetered_rate_limit(50, Ok(false)), Some(50));
+        assert_eq!(unmetered_rate_limit(50, Err(Error::ConfigLoad)), Some(50));
+    }
+
+    #[test]
+    pub fn test_check_payload() {
+        let profile = UploadConfig {
+            allowed_types: vec!["image/*".into(), "application/pdf".into()],
+            max_size: Some(4),
+            ..Default::default()
+        };
+
+        let image = Payload::new(PathBuf::from("image.png"), vec![0; 4], "image/png".into());
+        let pdf = Payload::new(PathBuf::from("doc.pdf"), vec![0; 2], "application/pdf".into());
+        let archive = Payload::new(PathBuf::from("logs.zip"), vec![0; 2], "application/zip".into());
+        let large = Payload::new(PathBuf::from("image.jpg"), vec![0; 8], "image/jpeg".into());
+
+        assert!(check_payload(&profile, &image).is_ok());
+        assert!(check_payload(&profile, &pdf).is_ok());
+        assert!(check_payload(&profile, &archive).is_err());
+        assert!(check_payload(&profile, &large).is_err());
+    }
 }
@@ -0,0 +1,215 @@
+use aes_gcm::{
+    aead::{consts::U16, rand_core::RngCore, Aead, KeyInit, OsRng, Payload as AeadPayload},
+    aes::Aes256,
+    AesGcm,
+};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{
+    header::CONTENT_TYPE,
+    multipart::{Form, Part},
+    RequestBuilder,
+};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::{
+    config::{PasteConfig, PasteService},
+    error::Error,
+    parser::parse_url,
+    payload::Payload,
+};
+
+use super::{
+    uploader::{Capabilities, Uploader},
+    CLIENT,
+};
+
+// PrivateBin encrypts pastes using AES-256-GCM with a 128 bit nonce
+type PrivateBinCipher = AesGcm<Aes256, U16>;
+
+const PRIVATEBIN_ITERATIONS: u32 = 100000;
+
+/// Uploads text pastes to one of the supported paste services.
+pub struct PasteUploader<'a> {
+    config: &'a PasteConfig,
+}
+
+impl<'a> PasteUploader<'a> {
+    pub fn new(config: &'a PasteConfig) -> Self {
+        Self { config }
+    }
+
+    /// Creates a request to the paste service including the configured headers.
+    fn request(&self, url: String) -> RequestBuilder {
+        let mut builder = CLIENT.post(url);
+        for (key, value) in &self.config.headers {
+            builder = builder.header(key, value);
+        }
+        builder
+    }
+}
+
+#[async_trait]
+impl Uploader for PasteUploader<'_> {
+    async fn upload(&self, payload: &Payload, _rate_limit: Option<u64>) -> Result<String, Error> {
+        let base_url = self.config.url.trim_end_matches('/');
+        let text = String::from_utf8_lossy(payload.bytes()).to_string();
+
+        match self.config.service {
+            PasteService::PrivateBin => {
+                let (body, key) = encrypt_privatebin(&text, self.config.expiration.as_deref())?;
+                let response = send(
+                    self.request(format!("{base_url}/"))
+                        .header("X-Requested-With", "JSONHttpRequest")
+                        .header(CONTENT_TYPE, "application/json")
+                        .body(body.to_string()),
+                )
+                .await?;
+
+                let response = serde_json::from_str::<Value>(&response)
+                    .map_err(|_| Error::InvalidResponse("Invalid json".into()))?;
+                if response["status"].as_i64() != Some(0) {
+                    let message = response["message"].as_str().unwrap_or("Unknown error");
+                    return Err(Error::InvalidResponse(message.to_string()));
+                }
+                let id = response["id"]
+                    .as_str()
+                    .ok_or(Error::InvalidResponse("Cannot find json value".into()))?;
+
+                // The key never leaves the client, it is only part of the shared url
+                Ok(format!(
+                    "{base_url}/?{id}#{}",
+                    bs58::encode(key).into_string()
+                ))
+            }
+            PasteService::Hastebin => {
+                let response =
+                    send(self.request(format!("{base_url}/documents")).body(text)).await?;
+                let key = parse_url(&response, &"$json:key$".into())?;
+                Ok(format!("{base_url}/{key}"))
+            }
+            PasteService::NullPointer => {
+                let part = Part::text(text)
+                    .file_name(payload.file_name())
+                    .mime_str(payload.mime_type())
+                    .map_err(|err| Error::from(err))?;
+                let response = send(
+                    self.request(format!("{base_url}/"))
+                        .multipart(Form::new().part("file", part)),
+                )
+                .await?;
+                Ok(response.trim().to_string())
+            }
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            delete: false,
+            rate_limit: false,
+            local: false,
+        }
+    }
+}
+
+/// Sends a request to the paste service.
+///
+/// # Returns
+/// The response body
+async fn send(builder: RequestBuilder) -> Result<String, Error> {
+    let response = builder.send().await.map_err(|err| Error::from(err))?;
+    let status = response.status();
+    let text = response.text().await.map_err(|err| Error::from(err))?;
+    if !status.is_success() {
+        return Err(Error::NonOkStatusCode(status.to_string(), text));
+    }
+    Ok(text)
+}
+
+/// Encrypts a paste using the format of the PrivateBin v2 API.
+///
+/// # Returns
+/// The request body and the key which has to be appended to the paste url
+fn encrypt_privatebin(text: &str, expiration: Option<&str>) -> Result<(Value, [u8; 32]), Error> {
+    let mut key = [0u8; 32];
+    let mut salt = [0u8; 8];
+    let mut nonce = [0u8; 16];
+    OsRng.fill_bytes(&mut key);
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut derived_key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(&key, &salt, PRIVATEBIN_ITERATIONS, &mut derived_key);
+
+    let adata = json!([
+        [
+            STANDARD.encode(nonce),
+            STANDARD.encode(salt),
+            PRIVATEBIN_ITERATIONS,
+            256,
+            128,
+            "aes",
+            "gcm",
+            "none"
+        ],
+        "plaintext",
+        0,
+        0
+    ]);
+    let plaintext = json!({ "paste": text }).to_string();
+
+    let ciphertext = PrivateBinCipher::new(&derived_key.into())
+        .encrypt(
+            &nonce.into(),
+            AeadPayload {
+                msg: plaintext.as_bytes(),
+                aad: adata.to_string().as_bytes(),
+            },
+        )
+        .map_err(|_| Error::Encryption)?;
+
+    let body = json!({
+        "v": 2,
+        "adata": adata,
+        "ct": STANDARD.encode(ciphertext),
+        "meta": { "expire": expiration.unwrap_or("1week") },
+    });
+    Ok((body, key))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use aes_gcm::aead::{Aead, KeyInit, Payload as AeadPayload};
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use sha2::Sha256;
+
+    use super::{encrypt_privatebin, PrivateBinCipher};
+
+    #[test]
+    pub fn test_privatebin_roundtrip() {
+        let (body, key) = encrypt_privatebin("hello", None).unwrap();
+        let parameters = &body["adata"][0];
+        let nonce = STANDARD.decode(parameters[0].as_str().unwrap()).unwrap();
+        let salt = STANDARD.decode(parameters[1].as_str().unwrap()).unwrap();
+        let ciphertext = STANDARD.decode(body["ct"].as_str().unwrap()).unwrap();
+
+        let mut derived_key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(&key, &salt, 100000, &mut derived_key);
+        let plaintext = PrivateBinCipher::new(&derived_key.into())
+            .decrypt(
+                nonce.as_slice().into(),
+                AeadPayload {
+                    msg: &ciphertext,
+                    aad: body["adata"].to_string().as_bytes(),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(plaintext).unwrap(),
+            r#"{"paste":"hello"}"#
+        );
+        assert_eq!(body["meta"]["expire"], "1week");
+    }
+}
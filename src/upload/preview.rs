@@ -0,0 +1,480 @@
+use std::{
+    fmt::{Display, Formatter},
+    path::Path,
+};
+
+use reqwest::{
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+    Request,
+};
+
+// Parts of header, field and query names which indicate a secret value
+const SENSITIVE_NAMES: [&str; 8] = [
+    "auth",
+    "cookie",
+    "key",
+    "password",
+    "secret",
+    "signature",
+    "token",
+    "credential",
+];
+// Authorization schemes which are kept visible when masking a value
+const AUTH_SCHEMES: [&str; 3] = ["Bearer", "Basic", "Token"];
+const MASK: &str = "********";
+const BASE64_PLACEHOLDER: &str = "{base64}";
+
+/// A part of a multipart form body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartPreview {
+    Text {
+        name: String,
+        value: String,
+    },
+    File {
+        name: String,
+        file_name: String,
+        mime_type: String,
+        size: usize,
+        path: String,
+    },
+}
+
+/// The layout of a request body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BodyPreview {
+    Empty,
+    Text(String),
+    // A text body embedding the base64 encoded file in place of `{base64}`
+    Base64 {
+        template: String,
+        url_encoded: bool,
+        size: usize,
+        path: String,
+    },
+    File {
+        mime_type: String,
+        size: usize,
+        path: String,
+    },
+    Multipart(Vec<PartPreview>),
+}
+
+/// A description of a request which would be sent to the upload server, with secrets masked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestPreview {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: BodyPreview,
+    // Explains how the curl command differs from the actual request
+    pub warning: Option<String>,
+}
+
+impl RequestPreview {
+    /// Creates a preview of a built request with the given body layout.
+    pub fn new(request: &Request, body: BodyPreview) -> Self {
+        let content_type = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        let mut url = request.url().clone();
+        let query = url
+            .query_pairs()
+            .map(|(key, value)| (key.to_string(), mask_secret(&key, &value)))
+            .collect::<Vec<_>>();
+        if !query.is_empty() {
+            url.query_pairs_mut().clear().extend_pairs(query);
+        }
+
+        Self {
+            method: request.method().to_string(),
+            url: url.to_string(),
+            headers: request
+                .headers()
+                .iter()
+                .map(|(key, value)| {
+                    let value = value.to_str().unwrap_or("<binary>");
+                    (key.to_string(), mask_secret(key.as_str(), value))
+                })
+                .collect(),
+            body: mask_body(body, content_type),
+            warning: None,
+        }
+    }
+
+    /// Replaces the file of the body with the original file, as the processed or encrypted file
+    /// only exists in memory.
+    pub fn use_original_file(&mut self, original: &Path) {
+        let original = original.display().to_string();
+        let mut replaced = false;
+        let mut replace = |path: &mut String| {
+            *path = original.clone();
+            replaced = true;
+        };
+        match &mut self.body {
+            BodyPreview::Base64 { path, .. } | BodyPreview::File { path, .. } => replace(path),
+            BodyPreview::Multipart(parts) => {
+                for part in parts {
+                    if let PartPreview::File { path, .. } = part {
+                        replace(path);
+                    }
+                }
+            }
+            BodyPreview::Empty | BodyPreview::Text(_) => {}
+        }
+
+        if replaced {
+            self.warning = Some(
+                "The profile processes or encrypts the file before uploading, \
+                 the curl command uploads the original file instead"
+                    .into(),
+            );
+        }
+    }
+
+    /// Converts the preview into an equivalent curl command.
+    pub fn to_curl(&self) -> String {
+        let mut arguments = vec![format!("curl -X {} {}", self.method, quote(&self.url))];
+        let comment = self
+            .warning
+            .as_ref()
+            .map(|warning| format!("# {warning}\n"))
+            .unwrap_or_default();
+
+        for (key, value) in &self.headers {
+            // curl calculates the length and generates its own multipart boundary
+            if key == CONTENT_LENGTH.as_str()
+                || (matches!(self.body, BodyPreview::Multipart(_)) && key == CONTENT_TYPE.as_str())
+            {
+                continue;
+            }
+            arguments.push(format!("-H {}", quote(&format!("{key}: {value}"))));
+        }
+
+        match &self.body {
+            BodyPreview::Empty => {}
+            BodyPreview::Text(text) => arguments.push(format!("--data-raw {}", quote(text))),
+            BodyPreview::Base64 {
+                template,
+                url_encoded,
+                path,
+                ..
+            } => {
+                // The file is encoded by the shell, `+`, `/` and `=` are escaped for forms
+                let escape = match url_encoded {
+                    true => " | sed 's/+/%2B/g; s|/|%2F|g; s/=/%3D/g'",
+                    false => "",
+                };
+                let encoded = format!("\"$(base64 -w0 {}{escape})\"", quote(path));
+                let data = template
+                    .split(BASE64_PLACEHOLDER)
+                    .map(quote)
+                    .collect::<Vec<_>>()
+                    .join(&encoded);
+                arguments.push(format!("--data-raw {data}"));
+            }
+            BodyPreview::File { path, .. } => {
+                arguments.push(format!("--data-binary {}", quote(&format!("@{path}"))))
+            }
+            BodyPreview::Multipart(parts) => {
+                for part in parts {
+                    arguments.push(format!(
+                        "-F {}",
+                        quote(&match part {
+                            PartPreview::Text { name, value } => format!("{name}={value}"),
+                            PartPreview::File {
+                                name,
+                                file_name,
+                                mime_type,
+                                path,
+                                ..
+                            } => format!("{name}=@{path};type={mime_type};filename={file_name}"),
+                        })
+                    ));
+                }
+            }
+        }
+
+        format!("{comment}{}", arguments.join(" \\\n  "))
+    }
+}
+
+impl Display for RequestPreview {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(warning) = &self.warning {
+            writeln!(f, "Note: {warning}")?;
+        }
+        writeln!(f, "{} {}", self.method, self.url)?;
+        for (key, value) in &self.headers {
+            writeln!(f, "{key}: {value}")?;
+        }
+        writeln!(f)?;
+
+        match &self.body {
+            BodyPreview::Empty => writeln!(f, "(empty body)"),
+            BodyPreview::Text(text) => writeln!(f, "{text}"),
+            BodyPreview::Base64 { template, size, .. } => writeln!(
+                f,
+                "{}",
+                template.replace(BASE64_PLACEHOLDER, &format!("<base64 data, {size} bytes>"))
+            ),
+            BodyPreview::File {
+                mime_type, size, ..
+            } => writeln!(f, "<file data, {mime_type}, {size} bytes>"),
+            BodyPreview::Multipart(parts) => {
+                writeln!(f, "Multipart form:")?;
+                for part in parts {
+                    match part {
+                        PartPreview::Text { name, value } => writeln!(f, "  {name} = \"{value}\"")?,
+                        PartPreview::File {
+                            name,
+                            file_name,
+                            mime_type,
+                            size,
+                            ..
+                        } => writeln!(
+                            f,
+                            "  {name} = <file \"{file_name}\", {mime_type}, {size} bytes>"
+                        )?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Checks whether a header, field or query parameter name indicates a secret value.
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SENSITIVE_NAMES
+        .iter()
+        .any(|sensitive| name.contains(sensitive))
+}
+
+/// Masks the value if its name indicates a secret, keeping authorization schemes visible.
+fn mask_secret(name: &str, value: &str) -> String {
+    if !is_sensitive(name) || value.is_empty() {
+        return value.to_string();
+    }
+
+    match value.split_once(' ') {
+        Some((scheme, _))
+            if AUTH_SCHEMES
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(scheme)) =>
+        {
+            format!("{scheme} {MASK}")
+        }
+        _ => MASK.to_string(),
+    }
+}
+
+/// Masks all secret fields of multipart, JSON and url-encoded form bodies.
+fn mask_body(body: BodyPreview, content_type: Option<&str>) -> BodyPreview {
+    match body {
+        BodyPreview::Text(text) => BodyPreview::Text(mask_text(text, content_type)),
+        BodyPreview::Base64 {
+            template,
+            url_encoded,
+            size,
+            path,
+        } => BodyPreview::Base64 {
+            template: mask_text(template, content_type),
+            url_encoded,
+            size,
+            path,
+        },
+        BodyPreview::Multipart(parts) => BodyPreview::Multipart(
+            parts
+                .into_iter()
+                .map(|part| match part {
+                    PartPreview::Text { name, value } => PartPreview::Text {
+                        value: mask_secret(&name, &value),
+                        name,
+                    },
+                    part => part,
+                })
+                .collect(),
+        ),
+        body => body,
+    }
+}
+
+/// Masks the secret fields of a text body, the format is taken from the content type.
+///
+/// Bodies without secrets are kept as they are, including their formatting.
+fn mask_text(text: String, content_type: Option<&str>) -> String {
+    let essence = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|essence| essence.trim().to_lowercase())
+        .unwrap_or_default();
+
+    match essence.as_str() {
+        "application/json" => {
+            let Ok(mut json) = serde_json::from_str::<serde_json::Value>(&text) else {
+                return text;
+            };
+            match mask_json(&mut json) {
+                true => json.to_string(),
+                false => text,
+            }
+        }
+        "application/x-www-form-urlencoded" => text
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((key, value))
+                    if is_sensitive(&urlencoding::decode(key).unwrap_or_default()) =>
+                {
+                    let value = urlencoding::decode(value).unwrap_or_default();
+                    let masked = mask_secret(&urlencoding::decode(key).unwrap_or_default(), &value);
+                    format!("{key}={}", urlencoding::encode(&masked))
+                }
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&"),
+        _ => text,
+    }
+}
+
+/// Masks all secret string values of a JSON value.
+///
+/// # Returns
+/// Whether any value was masked
+fn mask_json(json: &mut serde_json::Value) -> bool {
+    let mut masked = false;
+    match json {
+        serde_json::Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match value {
+                    serde_json::Value::String(secret)
+                        if is_sensitive(key) && !secret.is_empty() =>
+                    {
+                        *secret = mask_secret(key, secret);
+                        masked = true;
+                    }
+                    value => masked |= mask_json(value),
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                masked |= mask_json(value);
+            }
+        }
+        _ => {}
+    }
+    masked
+}
+
+/// Quotes a shell argument using single quotes.
+fn quote(argument: &str) -> String {
+    format!("'{}'", argument.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::Path;
+
+    use super::{mask_body, mask_secret, BodyPreview, PartPreview, RequestPreview};
+
+    #[test]
+    pub fn test_mask_secret() {
+        assert_eq!(
+            mask_secret("Authorization", "Bearer abc"),
+            "Bearer ********"
+        );
+        assert_eq!(mask_secret("X-Api-Key", "abc"), "********");
+        assert_eq!(
+            mask_secret("X-Api-Key", "correct horse battery"),
+            "********"
+        );
+        assert_eq!(
+            mask_secret("Accept", "application/json"),
+            "application/json"
+        );
+    }
+
+    #[test]
+    pub fn test_mask_body() {
+        let json = BodyPreview::Text(r#"{"image": "{base64}", "auth": {"token": "abc"}}"#.into());
+        let BodyPreview::Text(masked) = mask_body(json, Some("application/json")) else {
+            panic!("The body is not a text body");
+        };
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&masked).unwrap(),
+            serde_json::json!({"image": "{base64}", "auth": {"token": "********"}})
+        );
+
+        let form = BodyPreview::Text("image={base64}&api_key=a%20b&name=shot.png".into());
+        assert_eq!(
+            mask_body(
+                form,
+                Some("application/x-www-form-urlencoded; charset=utf-8")
+            ),
+            BodyPreview::Text(
+                "image={base64}&api_key=%2A%2A%2A%2A%2A%2A%2A%2A&name=shot.png".into()
+            )
+        );
+
+        let plain = BodyPreview::Text("token=abc".into());
+        assert_eq!(mask_body(plain.clone(), Some("text/plain")), plain);
+    }
+
+    #[test]
+    pub fn test_base64_curl() {
+        let preview = RequestPreview {
+            method: "POST".into(),
+            url: "https://example.com/upload".into(),
+            headers: Vec::new(),
+            body: BodyPreview::Base64 {
+                template: "image={base64}&name=shot.png".into(),
+                url_encoded: true,
+                size: 4,
+                path: "/tmp/shot.png".into(),
+            },
+            warning: None,
+        };
+        assert_eq!(
+            preview.to_curl(),
+            "curl -X POST 'https://example.com/upload' \\\n  --data-raw 'image='\"$(base64 -w0 '/tmp/shot.png' | sed 's/+/%2B/g; s|/|%2F|g; s/=/%3D/g')\"'&name=shot.png'"
+        );
+        assert_eq!(
+            preview.to_string(),
+            "POST https://example.com/upload\n\nimage=<base64 data, 4 bytes>&name=shot.png\n"
+        );
+    }
+
+    #[test]
+    pub fn test_use_original_file() {
+        let mut preview = RequestPreview {
+            method: "POST".into(),
+            url: "https://example.com/upload".into(),
+            headers: Vec::new(),
+            body: BodyPreview::Multipart(vec![PartPreview::File {
+                name: "file".into(),
+                file_name: "shot.webp".into(),
+                mime_type: "image/webp".into(),
+                size: 4,
+                path: "/tmp/shot.webp".into(),
+            }]),
+            warning: None,
+        };
+        preview.use_original_file(Path::new("/tmp/shot.png"));
+
+        let curl = preview.to_curl();
+        assert!(curl.starts_with("# The profile processes or encrypts the file"));
+        assert!(curl.ends_with("-F 'file=@/tmp/shot.png;type=image/webp;filename=shot.webp'"));
+
+        // Requests without a file stay unchanged
+        let mut preview = RequestPreview {
+            body: BodyPreview::Text("{}".into()),
+            warning: None,
+            ..preview
+        };
+        preview.use_original_file(Path::new("/tmp/shot.png"));
+        assert!(preview.warning.is_none());
+    }
+}
@@ -1,16 +1,27 @@
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
 use reqwest::{
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
     multipart::{Form, Part},
     RequestBuilder,
 };
 
 use crate::{
-    config::{UploadConfig, UploadStrategy},
-    image::Image,
+    config::{HttpConfig, RequestStep, UploadStrategy},
+    error::Error,
+    payload::Payload,
+    template::expand_template,
 };
 
-use super::CLIENT;
+use super::{
+    graphql::{graphql_operations, GRAPHQL_FILE_FIELD, GRAPHQL_FILE_MAP},
+    preview::{BodyPreview, PartPreview, RequestPreview},
+    throttle::throttled_body,
+    CLIENT,
+};
 
-/// Used to build a request for uploading an image to the upload server defined in the
+/// Used to build a request for uploading a payload to the upload server defined in the
 /// configuration file.
 ///
 /// # Example
@@ -24,13 +35,52 @@ use super::CLIENT;
 /// println!("body: {:#?}", response.text().await.unwrap());
 /// ```
 pub struct ImageUploadRequest<'a> {
-    config: &'a UploadConfig,
-    image: &'a Image,
+    config: &'a HttpConfig,
+    payload: &'a Payload,
+    rate_limit: Option<u64>,
 }
 
 impl<'a> ImageUploadRequest<'a> {
-    pub fn new(config: &'a UploadConfig, image: &'a Image) -> Self {
-        Self { config, image }
+    pub fn new(config: &'a HttpConfig, payload: &'a Payload) -> Self {
+        Self {
+            config,
+            payload,
+            rate_limit: None,
+        }
+    }
+
+    /// Limits the upload speed of the payload data to the given bytes per second.
+    pub fn with_rate_limit(mut self, rate_limit: Option<u64>) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    /// Expands the configured body template using the variables of the payload.
+    fn body_template(&self) -> String {
+        expand_body_template(
+            self.config.upload_strategy,
+            self.config.body_template.as_ref(),
+            self.config.graphql_query.as_ref(),
+            &self.payload.template_variables(),
+        )
+    }
+
+    /// Builds the request without sending it.
+    ///
+    /// # Returns
+    /// A description of the request with all secrets masked
+    pub fn preview(self) -> Result<RequestPreview, Error> {
+        let body = payload_body_preview(
+            self.config.upload_strategy,
+            self.config.file_form_name.clone(),
+            &self.config.form_fields,
+            self.body_template(),
+            self.payload,
+        );
+        let request = RequestBuilder::from(self)
+            .build()
+            .map_err(|err| Error::from(err))?;
+        Ok(RequestPreview::new(&request, body))
     }
 }
 
@@ -43,24 +93,320 @@ impl<'a> From<ImageUploadRequest<'a>> for RequestBuilder {
             builder = builder.header(key, value);
         }
 
-        // Yes, heavy operation but it seems like it cannot be avoided due to 'static requirements
-        let cloned_bytes = upload.image.bytes().clone();
-        builder = match upload.config.upload_strategy {
-            UploadStrategy::Body => builder.body(cloned_bytes),
-            UploadStrategy::Multipart => {
-                builder.multipart(
-                    Form::new().part(
-                        upload.config.file_form_name.clone().unwrap_or_default(),
-                        Part::bytes(cloned_bytes)
-                            // TODO: add support for file names
-                            .file_name("temp")
-                            .mime_str(&upload.image.mime_type())
-                            .unwrap(),
-                    ),
-                )
-            }
+        attach_image(
+            builder,
+            upload.config.upload_strategy,
+            upload.config.file_form_name.clone(),
+            upload.config.form_fields.clone(),
+            upload.body_template(),
+            upload.payload,
+            upload.rate_limit,
+        )
+    }
+}
+
+/// Used to build a single request of a multi-step upload chain.
+///
+/// All templated values of the step are expanded using the given variables.
+pub struct StepRequest<'a> {
+    step: &'a RequestStep,
+    payload: &'a Payload,
+    variables: &'a BTreeMap<String, String>,
+    rate_limit: Option<u64>,
+}
+
+impl<'a> StepRequest<'a> {
+    pub fn new(
+        step: &'a RequestStep,
+        payload: &'a Payload,
+        variables: &'a BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            step,
+            payload,
+            variables,
+            rate_limit: None,
+        }
+    }
+
+    /// Limits the upload speed of the payload data to the given bytes per second.
+    pub fn with_rate_limit(mut self, rate_limit: Option<u64>) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    /// Builds the request without sending it.
+    ///
+    /// # Returns
+    /// A description of the request with all secrets masked
+    pub fn preview(self) -> Result<RequestPreview, Error> {
+        let expand = |value: &String| expand_template(value, self.variables);
+        let body = match self.step.upload_strategy {
+            Some(upload_strategy) => payload_body_preview(
+                upload_strategy,
+                self.step.file_form_name.clone(),
+                &self
+                    .step
+                    .form_fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), expand(value)))
+                    .collect(),
+                expand_body_template(
+                    upload_strategy,
+                    self.step.body.as_ref(),
+                    self.step.graphql_query.as_ref(),
+                    self.variables,
+                ),
+                self.payload,
+            ),
+            None => match &self.step.body {
+                Some(body) => BodyPreview::Text(expand(body)),
+                None => BodyPreview::Empty,
+            },
         };
+        let request = RequestBuilder::from(self)
+            .build()
+            .map_err(|err| Error::from(err))?;
+        Ok(RequestPreview::new(&request, body))
+    }
+}
+
+impl<'a> From<StepRequest<'a>> for RequestBuilder {
+    fn from(request: StepRequest) -> RequestBuilder {
+        let expand = |value: &String| expand_template(value, request.variables);
+        let mut builder = CLIENT.request(
+            (&request.step.request_method).into(),
+            expand(&request.step.url),
+        );
+
+        for (key, value) in &request.step.headers {
+            builder = builder.header(key, expand(value));
+        }
+
+        match request.step.upload_strategy {
+            Some(upload_strategy) => attach_image(
+                builder,
+                upload_strategy,
+                request.step.file_form_name.clone(),
+                request
+                    .step
+                    .form_fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), expand(value)))
+                    .collect(),
+                expand_body_template(
+                    upload_strategy,
+                    request.step.body.as_ref(),
+                    request.step.graphql_query.as_ref(),
+                    request.variables,
+                ),
+                request.payload,
+                request.rate_limit,
+            ),
+            None => match &request.step.body {
+                Some(body) => builder.body(expand(body)),
+                None => builder,
+            },
+        }
+    }
+}
+
+/// Attaches the payload data to a request using the given upload strategy.
+///
+/// The form fields are only sent when using the multipart strategy, the body template (expanded
+/// using `expand_body_template`) only when using the `Json`, `UrlEncodedForm` or `GraphQl`
+/// strategy.
+/// If a rate limit is given, the payload data is streamed with at most that many bytes per second.
+fn attach_image(
+    builder: RequestBuilder,
+    upload_strategy: UploadStrategy,
+    file_form_name: Option<String>,
+    form_fields: BTreeMap<String, String>,
+    body_template: String,
+    payload: &Payload,
+    rate_limit: Option<u64>,
+) -> RequestBuilder {
+    // Yes, heavy operation but it seems like it cannot be avoided due to 'static requirements
+    let cloned_bytes = match upload_strategy.content_type() {
+        Some(_) => {
+            let base64 = escape_template_value(upload_strategy, &STANDARD.encode(payload.bytes()));
+            body_template.replace("{base64}", &base64).into_bytes()
+        }
+        None => payload.bytes().clone(),
+    };
+    let length = cloned_bytes.len() as u64;
+    match upload_strategy {
+        UploadStrategy::Multipart | UploadStrategy::GraphQl => {
+            let (form, file_form_name) = match upload_strategy {
+                UploadStrategy::GraphQl => (
+                    Form::new()
+                        .text("operations", body_template)
+                        .text("map", GRAPHQL_FILE_MAP),
+                    GRAPHQL_FILE_FIELD.to_string(),
+                ),
+                _ => (
+                    form_fields
+                        .into_iter()
+                        .fold(Form::new(), |form, (key, value)| form.text(key, value)),
+                    file_form_name.unwrap_or_default(),
+                ),
+            };
+            let part = match rate_limit {
+                Some(rate_limit) => {
+                    Part::stream_with_length(throttled_body(cloned_bytes, rate_limit), length)
+                }
+                None => Part::bytes(cloned_bytes),
+            };
+            builder.multipart(
+                form.part(
+                    file_form_name,
+                    part.file_name(payload.file_name())
+                        .mime_str(&payload.mime_type())
+                        .unwrap(),
+                ),
+            )
+        }
+        _ => {
+            let builder = match upload_strategy.content_type() {
+                Some(content_type) => builder.header(CONTENT_TYPE, content_type),
+                None => builder,
+            };
+            match rate_limit {
+                Some(rate_limit) => builder
+                    .header(CONTENT_LENGTH, length)
+                    .body(throttled_body(cloned_bytes, rate_limit)),
+                None => builder.body(cloned_bytes),
+            }
+        }
+    }
+}
+
+/// Expands the body template of the `Json`, `UrlEncodedForm` and `GraphQl` strategies, escaping
+/// all variables for the format of the body.
+///
+/// `{base64}` is kept, it is only replaced once the payload data is attached.
+///
+/// # Returns
+/// The expanded template (the `operations` field for `GraphQl`), empty for strategies without
+/// body template
+fn expand_body_template(
+    upload_strategy: UploadStrategy,
+    body_template: Option<&String>,
+    graphql_query: Option<&String>,
+    variables: &BTreeMap<String, String>,
+) -> String {
+    let template = match body_template {
+        Some(body_template) => body_template.as_str(),
+        None => upload_strategy.default_body_template().unwrap_or_default(),
+    };
+    let variables = variables
+        .iter()
+        .filter(|(key, _)| *key != "base64")
+        .map(|(key, value)| (key.clone(), escape_template_value(upload_strategy, value)))
+        .collect();
+    let body = expand_template(template, &variables);
+
+    match upload_strategy {
+        UploadStrategy::GraphQl => {
+            graphql_operations(graphql_query.map(String::as_str).unwrap_or_default(), &body)
+        }
+        _ => body,
+    }
+}
+
+/// Escapes a value inserted into the body template of the given strategy.
+fn escape_template_value(upload_strategy: UploadStrategy, value: &str) -> String {
+    match upload_strategy {
+        UploadStrategy::Json | UploadStrategy::GraphQl => {
+            let quoted = serde_json::Value::from(value).to_string();
+            quoted[1..quoted.len() - 1].to_string()
+        }
+        UploadStrategy::UrlEncodedForm => urlencoding::encode(value).into_owned(),
+        _ => value.to_string(),
+    }
+}
+
+/// Describes the body created by `attach_image` for previews.
+fn payload_body_preview(
+    upload_strategy: UploadStrategy,
+    file_form_name: Option<String>,
+    form_fields: &BTreeMap<String, String>,
+    body_template: String,
+    payload: &Payload,
+) -> BodyPreview {
+    let path = payload.path().display().to_string();
+    match upload_strategy {
+        UploadStrategy::Json | UploadStrategy::UrlEncodedForm => BodyPreview::Base64 {
+            template: body_template,
+            url_encoded: upload_strategy == UploadStrategy::UrlEncodedForm,
+            size: payload.bytes().len(),
+            path,
+        },
+        UploadStrategy::Body => BodyPreview::File {
+            mime_type: payload.mime_type().clone(),
+            size: payload.bytes().len(),
+            path,
+        },
+        UploadStrategy::GraphQl => BodyPreview::Multipart(vec![
+            PartPreview::Text {
+                name: "operations".into(),
+                value: body_template,
+            },
+            PartPreview::Text {
+                name: "map".into(),
+                value: GRAPHQL_FILE_MAP.into(),
+            },
+            PartPreview::File {
+                name: GRAPHQL_FILE_FIELD.into(),
+                file_name: payload.file_name(),
+                mime_type: payload.mime_type().clone(),
+                size: payload.bytes().len(),
+                path,
+            },
+        ]),
+        UploadStrategy::Multipart => {
+            let mut parts = form_fields
+                .iter()
+                .map(|(name, value)| PartPreview::Text {
+                    name: name.clone(),
+                    value: value.clone(),
+                })
+                .collect::<Vec<_>>();
+            parts.push(PartPreview::File {
+                name: file_form_name.unwrap_or_default(),
+                file_name: payload.file_name(),
+                mime_type: payload.mime_type().clone(),
+                size: payload.bytes().len(),
+                path,
+            });
+            BodyPreview::Multipart(parts)
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::config::UploadStrategy;
+
+    use super::expand_body_template;
+
+    #[test]
+    pub fn test_expand_body_template() {
+        let variables = BTreeMap::from([("file_name".to_string(), "a \"b\" c.png".to_string())]);
 
-        builder
+        assert_eq!(
+            expand_body_template(UploadStrategy::Json, None, None, &variables),
+            r#"{"image": "{base64}", "name": "a \"b\" c.png"}"#
+        );
+        assert_eq!(
+            expand_body_template(UploadStrategy::UrlEncodedForm, None, None, &variables),
+            "image={base64}&name=a%20%22b%22%20c.png"
+        );
+        assert_eq!(
+            expand_body_template(UploadStrategy::Body, None, None, &variables),
+            ""
+        );
     }
 }
@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+
+use reqwest::header::CONTENT_TYPE;
+
+use crate::{
+    config::{ShortenerConfig, ShortenerType},
+    error::Error,
+    parser::parse_url,
+    template::expand_template,
+};
+
+use super::CLIENT;
+
+/// Shortens an uploaded payload url using the configured URL shortener.
+///
+/// # Returns
+/// The shortened url
+pub async fn shorten_url(config: &ShortenerConfig, url: &str) -> Result<String, Error> {
+    let base_url = config.url.trim_end_matches('/');
+    let variables = BTreeMap::from([
+        ("url".to_string(), url.to_string()),
+        ("url_encoded".to_string(), urlencoding::encode(url).to_string()),
+    ]);
+    let mut builder = match config.shortener_type {
+        ShortenerType::Yourls => {
+            let mut form = vec![("action", "shorturl"), ("format", "json"), ("url", url)];
+            if let Some(api_key) = &config.api_key {
+                form.push(("signature", api_key));
+            }
+            CLIENT.post(base_url).form(&form)
+        }
+        ShortenerType::Shlink => CLIENT
+            .post(format!("{base_url}/rest/v3/short-urls"))
+            .header("X-Api-Key", config.api_key.clone().unwrap_or_default())
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::json!({ "longUrl": url }).to_string()),
+        ShortenerType::Kutt => CLIENT
+            .post(format!("{base_url}/api/v2/links"))
+            .header("X-API-KEY", config.api_key.clone().unwrap_or_default())
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::json!({ "target": url }).to_string()),
+        ShortenerType::Template => {
+            let mut builder = CLIENT.request(
+                (&config.request_method).into(),
+                expand_template(&config.url, &variables),
+            );
+            if let Some(body) = &config.body {
+                builder = builder.body(expand_template(body, &variables));
+            }
+            builder
+        }
+    };
+
+    for (key, value) in &config.headers {
+        builder = builder.header(key, expand_template(value, &variables));
+    }
+
+    let response = builder.send().await.map_err(|err| Error::from(err))?;
+    let status = response.status();
+    let text = response.text().await.map_err(|err| Error::from(err))?;
+    if !status.is_success() {
+        return Err(Error::NonOkStatusCode(status.to_string(), text));
+    }
+
+    let url_parser = config
+        .url_parser
+        .clone()
+        .unwrap_or(config.shortener_type.default_url_parser().to_string());
+    Ok(parse_url(&text, &url_parser)?.trim().to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        config::{RequestMethod, ShortenerConfig, ShortenerType},
+        upload::tests::{response, serve},
+    };
+
+    use super::shorten_url;
+
+    const LONG_URL: &str = "https://example.com/image.png?a=1";
+
+    /// Shortens the long url using a local server which returns the given response body.
+    ///
+    /// # Returns
+    /// The shortened url and the request received by the server
+    async fn shorten(mut config: ShortenerConfig, body: &str) -> (String, String) {
+        let (url, requests) = serve(vec![response("200 OK", "application/json", body)]).await;
+        config.url = config.url.replace("{server}", &url);
+        let short_url = shorten_url(&config, LONG_URL).await.unwrap();
+        (short_url, requests.await.unwrap().remove(0))
+    }
+
+    #[tokio::test]
+    pub async fn test_shortener_types() {
+        let config = |shortener_type| ShortenerConfig {
+            shortener_type,
+            url: "{server}/".into(),
+            api_key: Some("secret".into()),
+            ..Default::default()
+        };
+
+        let (short_url, request) = shorten(
+            config(ShortenerType::Yourls),
+            r#"{"shorturl": "https://sho.rt/y"}"#,
+        )
+        .await;
+        assert_eq!(short_url, "https://sho.rt/y");
+        assert!(request.starts_with("post / "));
+        assert!(request.ends_with(
+            "action=shorturl&format=json&url=https%3a%2f%2fexample.com%2fimage.png%3fa%3d1&signature=secret"
+        ));
+
+        let (short_url, request) = shorten(
+            config(ShortenerType::Shlink),
+            r#"{"shortUrl": "https://sho.rt/s"}"#,
+        )
+        .await;
+        assert_eq!(short_url, "https://sho.rt/s");
+        assert!(request.starts_with("post /rest/v3/short-urls "));
+        assert!(request.contains("x-api-key: secret"));
+        assert!(request.ends_with(r#"{"longurl":"https://example.com/image.png?a=1"}"#));
+
+        let (short_url, request) = shorten(
+            config(ShortenerType::Kutt),
+            r#"{"link": "https://sho.rt/k"}"#,
+        )
+        .await;
+        assert_eq!(short_url, "https://sho.rt/k");
+        assert!(request.starts_with("post /api/v2/links "));
+        assert!(request.contains("x-api-key: secret"));
+        assert!(request.ends_with(r#"{"target":"https://example.com/image.png?a=1"}"#));
+    }
+
+    #[tokio::test]
+    pub async fn test_template_shortener() {
+        let config = ShortenerConfig {
+            shortener_type: ShortenerType::Template,
+            url: "{server}/shorten?url={url_encoded}".into(),
+            request_method: RequestMethod::Post,
+            headers: BTreeMap::from([("X-Long-Url".to_string(), "{url}".to_string())]),
+            body: Some("long={url}".into()),
+            ..Default::default()
+        };
+
+        // The raw response is trimmed
+        let (short_url, request) = shorten(config, "https://sho.rt/t\n").await;
+        assert_eq!(short_url, "https://sho.rt/t");
+        assert!(
+            request.starts_with("post /shorten?url=https%3a%2f%2fexample.com%2fimage.png%3fa%3d1 ")
+        );
+        assert!(request.contains("x-long-url: https://example.com/image.png?a=1"));
+        assert!(request.ends_with("long=https://example.com/image.png?a=1"));
+    }
+}
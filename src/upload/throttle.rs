@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::Stream;
+use reqwest::Body;
+use tokio::time::Instant;
+
+// The amount of chunks sent per second, smaller chunks result in a smoother rate
+const CHUNKS_PER_SECOND: u64 = 10;
+
+/// Creates a request body which is streamed with at most `bytes_per_second`.
+///
+/// # Returns
+/// The throttled request body
+pub fn throttled_body(bytes: Vec<u8>, bytes_per_second: u64) -> Body {
+    Body::wrap_stream(throttled_stream(bytes, bytes_per_second))
+}
+
+/// Splits the bytes into chunks which are yielded with at most `bytes_per_second`.
+fn throttled_stream(
+    bytes: Vec<u8>,
+    bytes_per_second: u64,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+    let bytes_per_second = bytes_per_second.max(1);
+    let chunk_size = (bytes_per_second / CHUNKS_PER_SECOND).max(1) as usize;
+
+    futures_util::stream::unfold(
+        (Bytes::from(bytes), 0, None),
+        move |(bytes, sent, start): (Bytes, usize, Option<Instant>)| async move {
+            if sent >= bytes.len() {
+                return None;
+            }
+
+            // Waits until the previously sent bytes are within the allowed rate
+            let start = start.unwrap_or_else(Instant::now);
+            tokio::time::sleep_until(
+                start + Duration::from_secs_f64(sent as f64 / bytes_per_second as f64),
+            )
+            .await;
+
+            let end = (sent + chunk_size).min(bytes.len());
+            let chunk = bytes.slice(sent..end);
+            Some((
+                Ok::<Bytes, std::io::Error>(chunk),
+                (bytes, end, Some(start)),
+            ))
+        },
+    )
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+    use tokio::time::Instant;
+
+    use super::throttled_stream;
+
+    #[tokio::test]
+    pub async fn test_throttled_stream() {
+        let start = Instant::now();
+        let chunks: Vec<usize> = throttled_stream(vec![0; 45], 100)
+            .map(|chunk| chunk.unwrap().len())
+            .collect()
+            .await;
+
+        // 10 chunks per second, the last one is sent once 40 bytes were allowed
+        assert_eq!(chunks, vec![10, 10, 10, 10, 5]);
+        assert!(start.elapsed() >= Duration::from_millis(400));
+
+        // Rates below the chunk rate still send at least a byte per chunk
+        let chunks = throttled_stream(vec![0; 2], 5).collect::<Vec<_>>().await;
+        assert_eq!(chunks.len(), 2);
+    }
+}
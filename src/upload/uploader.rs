@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+
+use crate::{
+    config::{BackendType, UploadConfig},
+    error::Error,
+    payload::Payload,
+};
+
+use super::{
+    data_uri::DataUriUploader, http::HttpUploader, paste::PasteUploader, preview::RequestPreview,
+};
+
+/// The optional features supported by an upload backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    // Uploaded images can be deleted using `Uploader::delete`
+    pub delete: bool,
+    // The upload speed can be limited
+    pub rate_limit: bool,
+    // The url is created without contacting any server, e.g. a data URI
+    pub local: bool,
+}
+
+/// A backend which is able to upload images.
+#[async_trait]
+pub trait Uploader: Send + Sync {
+    /// Uploads a payload, limiting the upload speed to `rate_limit` bytes per second if supported.
+    ///
+    /// # Returns
+    /// The url to the uploaded payload
+    async fn upload(&self, payload: &Payload, rate_limit: Option<u64>) -> Result<String, Error>;
+
+    /// Deletes a previously uploaded payload.
+    async fn delete(&self, _url: &str) -> Result<(), Error> {
+        Err(Error::Unsupported("delete"))
+    }
+
+    /// Builds the requests an upload would make without sending them.
+    ///
+    /// # Returns
+    /// The requests in the order they would be sent, with all secrets masked
+    fn preview(&self, _payload: &Payload) -> Result<Vec<RequestPreview>, Error> {
+        Err(Error::Unsupported("request previews"))
+    }
+
+    /// The optional features supported by this backend.
+    fn capabilities(&self) -> Capabilities;
+}
+
+/// Creates the uploader for the backend selected by the profile.
+///
+/// # Returns
+/// The uploader configured using the profile's backend section
+pub fn create_uploader(config: &UploadConfig) -> Box<dyn Uploader + '_> {
+    match config.backend {
+        BackendType::Http => Box::new(HttpUploader::new(&config.http)),
+        BackendType::Paste => Box::new(PasteUploader::new(&config.paste)),
+        BackendType::DataUri => Box::new(DataUriUploader::new(&config.data_uri)),
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use reqwest::{header::CONTENT_TYPE, Method};
+use tokio::time::Instant;
+
+use crate::{
+    config::{VerifyConfig, VerifyMethod},
+    error::Error,
+    payload::mime_matches,
+};
+
+use super::CLIENT;
+
+// Every attempt may take at least this long, even if the deadline is closer
+const MIN_ATTEMPT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Verifies that an uploaded url is reachable and serves the expected content type.
+///
+/// Failed checks are repeated until the configured timeout is reached, as some hosts return
+/// the url before it can be served.
+pub async fn verify_url(config: &VerifyConfig, url: &str, mime_type: &str) -> Result<(), Error> {
+    let expected = config.content_type.as_deref().unwrap_or(mime_type);
+    let interval = Duration::from_secs(config.interval);
+    let deadline = Instant::now() + Duration::from_secs(config.timeout);
+
+    loop {
+        let timeout = deadline
+            .saturating_duration_since(Instant::now())
+            .max(MIN_ATTEMPT_TIMEOUT);
+        match check_url(config.request_method, url, expected, timeout).await {
+            Ok(()) => return Ok(()),
+            Err(reason) if Instant::now() + interval < deadline => {
+                log::info!("Uploaded url is not available yet: {reason}");
+                tokio::time::sleep(interval).await;
+            }
+            Err(reason) => return Err(Error::VerificationFailed(url.to_string(), reason)),
+        }
+    }
+}
+
+/// Requests the url once, giving up after the timeout.
+///
+/// # Returns
+/// The reason if the url is not reachable or serves an unexpected content type
+async fn check_url(
+    method: VerifyMethod,
+    url: &str,
+    expected: &str,
+    timeout: Duration,
+) -> Result<(), String> {
+    let method = match method {
+        VerifyMethod::Head => Method::HEAD,
+        VerifyMethod::Get => Method::GET,
+    };
+    let response = CLIENT
+        .request(method, url)
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("server responded with {status}"));
+    }
+
+    // Ignore parameters like `; charset=utf-8`
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .unwrap_or_default()
+        .trim();
+    if !mime_matches(expected, content_type) {
+        return Err(format!(
+            "unexpected content type '{content_type}', expected '{expected}'"
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use tokio::{net::TcpListener, time::Instant};
+
+    use crate::{
+        config::{VerifyConfig, VerifyMethod},
+        error::Error,
+        upload::tests::{response, serve},
+    };
+
+    use super::{check_url, verify_url};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[tokio::test]
+    pub async fn test_check_url() {
+        let (url, _) = serve(vec![
+            response("200 OK", "image/png; charset=binary", ""),
+            response("200 OK", "text/html; charset=utf-8", ""),
+            response("404 Not Found", "image/png", ""),
+        ])
+        .await;
+
+        assert!(check_url(VerifyMethod::Head, &url, "image/*", TIMEOUT)
+            .await
+            .is_ok());
+        let reason = check_url(VerifyMethod::Get, &url, "image/png", TIMEOUT)
+            .await
+            .unwrap_err();
+        assert!(reason.contains("'text/html'"));
+        let reason = check_url(VerifyMethod::Head, &url, "image/png", TIMEOUT)
+            .await
+            .unwrap_err();
+        assert!(reason.contains("404"));
+    }
+
+    #[tokio::test]
+    pub async fn test_verify_url() {
+        // The url only becomes available on the second attempt
+        let (url, requests) = serve(vec![
+            response("404 Not Found", "text/plain", "not found"),
+            response("200 OK", "image/png", ""),
+        ])
+        .await;
+        let config = VerifyConfig {
+            timeout: 5,
+            interval: 1,
+            ..Default::default()
+        };
+        assert!(verify_url(&config, &url, "image/png").await.is_ok());
+        assert_eq!(requests.await.unwrap().len(), 2);
+
+        // Failed checks are not repeated once the deadline is reached
+        let (url, requests) = serve(vec![response("200 OK", "text/html", "")]).await;
+        let config = VerifyConfig {
+            timeout: 0,
+            ..Default::default()
+        };
+        let result = verify_url(&config, &url, "image/png").await;
+        assert!(matches!(result, Err(Error::VerificationFailed(..))));
+        assert_eq!(requests.await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    pub async fn test_verify_url_deadline() {
+        // Accepts connections but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut connections = Vec::new();
+            loop {
+                connections.push(listener.accept().await.unwrap());
+            }
+        });
+
+        let start = Instant::now();
+        let config = VerifyConfig {
+            timeout: 2,
+            interval: 1,
+            ..Default::default()
+        };
+        let result = verify_url(&config, &url, "image/png").await;
+        assert!(matches!(result, Err(Error::VerificationFailed(..))));
+        assert!(start.elapsed() < Duration::from_secs(4));
+        server.abort();
+    }
+}